@@ -1,8 +1,21 @@
+#![allow(unexpected_cfgs)] // cfgs emitted by the anchor program macros
+
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
 
 declare_id!("xF4A8Ksy6WSzJpskfiVUit4osedmBorP3bgDe9uKu2e");
 
 pub const DEVICE_FEED_SEED: &[u8] = b"device_feed";
+pub const FEED_HISTORY_SEED: &[u8] = b"feed_history";
+/// Hourly buckets kept per feed (7 days).
+pub const FEED_HISTORY_HOURS: usize = 168;
+pub const SECONDS_PER_HOUR: i64 = 3600;
+pub const MAX_FEED_KEEPERS: usize = 8;
+/// Prefix of the payload a device signs for each update.
+pub const DEVICE_ATTESTATION_DOMAIN: &[u8] = b"soulboard-feed";
 
 #[program]
 pub mod oracle {
@@ -20,8 +33,21 @@ pub mod oracle {
         feed.last_entry_id = 0;
         feed.total_views = 0;
         feed.total_taps = 0;
-        feed.last_update_ts = 0;
+        // Rate limits measure the first update from the feed's creation
+        feed.last_update_ts = Clock::get()?.unix_timestamp;
         feed.authority = *ctx.accounts.authority.key;
+        feed.pending_authority = Pubkey::default();
+        feed.keepers = Vec::new();
+        feed.threshold = 0;
+        clear_pending_update(feed);
+        feed.device_key = Pubkey::default();
+        feed.has_history = false;
+        feed.max_views_per_second = 0;
+        feed.min_update_interval = 0;
+        feed.taps_within_views = false;
+        feed.anomaly_count = 0;
+        feed.last_anomaly_ts = 0;
+        feed.limits_authority = Pubkey::default();
 
         emit!(DeviceFeedInitialized {
             // NEW
@@ -33,6 +59,10 @@ pub mod oracle {
     }
 
     /// Keeper pushes deltas since `last_entry_id`. We store **running totals**.
+    /// With a keeper quorum set, each call is one attestation and the update is
+    /// only committed once `threshold` distinct keepers sent the same values.
+    /// Conflicting submissions are tracked side by side rather than replacing
+    /// each other.
     pub fn update_device_feed(
         ctx: Context<UpdateDeviceFeed>,
         _channel_id: u32,
@@ -42,13 +72,87 @@ pub mod oracle {
     ) -> Result<()> {
         let clock = Clock::get()?;
         let feed = &mut ctx.accounts.feed;
+        let signer = ctx.accounts.signer.key();
 
         require!(newest_entry_id > feed.last_entry_id, OracleErr::NoNewData);
-        require_keys_eq!(
-            ctx.accounts.signer.key(),
-            feed.authority,
-            OracleErr::BadAuthority
-        );
+        require_device_signature(
+            feed,
+            ctx.accounts.instructions.as_ref(),
+            newest_entry_id,
+            delta_views,
+            delta_taps,
+        )?;
+
+        if feed.threshold == 0 {
+            require_keys_eq!(signer, feed.authority, OracleErr::BadAuthority);
+        } else {
+            let keeper_index = feed
+                .keepers
+                .iter()
+                .position(|keeper| *keeper == signer)
+                .ok_or(OracleErr::BadAuthority)?;
+            let keeper_bit = 1u8 << keeper_index;
+
+            // Each keeper backs one candidate at a time and may only move its vote
+            // to a newer entry, so no keeper can reset the others' attestations
+            if let Some(current) = feed
+                .pending_updates
+                .iter()
+                .find(|update| update.attestations & keeper_bit != 0)
+            {
+                require!(
+                    newest_entry_id > current.entry_id,
+                    OracleErr::AlreadyAttested
+                );
+            }
+            for update in feed.pending_updates.iter_mut() {
+                update.attestations &= !keeper_bit;
+            }
+            feed.pending_updates
+                .retain(|update| update.attestations != 0);
+
+            let attestations = match feed.pending_updates.iter_mut().find(|update| {
+                update.entry_id == newest_entry_id
+                    && update.delta_views == delta_views
+                    && update.delta_taps == delta_taps
+            }) {
+                Some(update) => {
+                    update.attestations |= keeper_bit;
+                    update.attestations.count_ones() as u8
+                }
+                None => {
+                    feed.pending_updates.push(PendingUpdate {
+                        entry_id: newest_entry_id,
+                        delta_views,
+                        delta_taps,
+                        attestations: keeper_bit,
+                    });
+                    1
+                }
+            };
+
+            emit!(FeedUpdateAttested {
+                channel_id: feed.channel_id,
+                keeper: signer,
+                entry_id: newest_entry_id,
+                attestations,
+                threshold: feed.threshold,
+            });
+            if attestations < feed.threshold {
+                return Ok(());
+            }
+            // Candidates at or below the committed entry can never land now
+            feed.pending_updates
+                .retain(|update| update.entry_id > newest_entry_id);
+        }
+
+        // Limits apply to what is committed, once the update is known to be
+        // authorised. A rejected update can be put on record with report_feed_anomaly.
+        if let Some((kind, _)) =
+            feed_limit_violation(feed, delta_views, delta_taps, clock.unix_timestamp)
+        {
+            return Err(kind.error().into());
+        }
 
         feed.total_views = feed
             .total_views
@@ -59,6 +163,16 @@ pub mod oracle {
             .checked_add(delta_taps)
             .ok_or(OracleErr::Overflow)?;
 
+        // Once a feed keeps history, every committed delta must land in it
+        if feed.has_history {
+            let history = ctx
+                .accounts
+                .history
+                .as_mut()
+                .ok_or(OracleErr::MissingFeedHistory)?;
+            record_in_history(history, clock.unix_timestamp, delta_views, delta_taps)?;
+        }
+
         feed.last_entry_id = newest_entry_id;
         feed.last_update_ts = clock.unix_timestamp;
         emit!(DeviceFeedUpdated {
@@ -73,19 +187,516 @@ pub mod oracle {
         });
         Ok(())
     }
+
+    /// Authority or a keeper puts an update the feed's limits reject on
+    /// record: the feed counts it and emits a FeedAnomaly, while the totals
+    /// stay untouched. Reporting is authorised like the update itself, so a
+    /// device-signed feed can only be flagged with counts the screen signed.
+    pub fn report_feed_anomaly(
+        ctx: Context<ReportFeedAnomaly>,
+        _channel_id: u32,
+        entry_id: u32,
+        delta_views: u64,
+        delta_taps: u64,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        let signer = ctx.accounts.signer.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(entry_id > feed.last_entry_id, OracleErr::NoNewData);
+        require!(
+            signer == feed.authority || feed.keepers.contains(&signer),
+            OracleErr::BadAuthority
+        );
+        require_device_signature(
+            feed,
+            ctx.accounts.instructions.as_ref(),
+            entry_id,
+            delta_views,
+            delta_taps,
+        )?;
+
+        let (kind, elapsed) = feed_limit_violation(feed, delta_views, delta_taps, now)
+            .ok_or(OracleErr::WithinFeedLimits)?;
+        feed.anomaly_count = feed.anomaly_count.saturating_add(1);
+        feed.last_anomaly_ts = now;
+
+        emit!(FeedAnomaly {
+            channel_id: feed.channel_id,
+            signer,
+            entry_id,
+            delta_views,
+            delta_taps,
+            elapsed,
+            kind,
+        });
+        Ok(())
+    }
+
+    /// Authority starts hourly history for the feed. Deltas committed from now
+    /// on are also bucketed by the hour they were recorded in; the totals up
+    /// to this point are kept so readers can bridge the switch.
+    pub fn initialize_feed_history(
+        ctx: Context<InitializeFeedHistory>,
+        channel_id: u32,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            feed.authority,
+            OracleErr::BadAuthority
+        );
+
+        let history = &mut ctx.accounts.history;
+        history.channel_id = channel_id;
+        history.bump = ctx.bumps.history;
+        history.started_at = Clock::get()?.unix_timestamp;
+        history.start_views = feed.total_views;
+        history.start_taps = feed.total_taps;
+        history.buckets = vec![FeedBucket::default(); FEED_HISTORY_HOURS];
+        feed.has_history = true;
+
+        emit!(FeedHistoryInitialized {
+            channel_id,
+            ts: history.started_at,
+        });
+        Ok(())
+    }
+
+    /// Current authority replaces itself straight away (e.g. a compromised keeper key).
+    pub fn set_feed_authority(
+        ctx: Context<SetFeedAuthority>,
+        _channel_id: u32,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            feed.authority,
+            OracleErr::BadAuthority
+        );
+        require_keys_neq!(new_authority, Pubkey::default(), OracleErr::BadAuthority);
+
+        let previous_authority = feed.authority;
+        feed.authority = new_authority;
+        feed.pending_authority = Pubkey::default();
+
+        emit!(FeedAuthorityTransferred {
+            channel_id: feed.channel_id,
+            previous_authority,
+            new_authority,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Authority sets the keeper quorum. An empty list with threshold 0 goes
+    /// back to single-authority updates. Any pending attestations are dropped.
+    pub fn set_feed_keepers(
+        ctx: Context<SetFeedKeepers>,
+        _channel_id: u32,
+        keepers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            feed.authority,
+            OracleErr::BadAuthority
+        );
+        require!(keepers.len() <= MAX_FEED_KEEPERS, OracleErr::TooManyKeepers);
+        for (i, keeper) in keepers.iter().enumerate() {
+            require!(!keepers[..i].contains(keeper), OracleErr::DuplicateKeeper);
+        }
+        let valid_threshold = if keepers.is_empty() {
+            threshold == 0
+        } else {
+            threshold >= 1 && threshold as usize <= keepers.len()
+        };
+        require!(valid_threshold, OracleErr::InvalidThreshold);
+
+        feed.keepers = keepers;
+        feed.threshold = threshold;
+        clear_pending_update(feed);
+
+        emit!(FeedKeepersSet {
+            channel_id: feed.channel_id,
+            keepers: feed.keepers.clone(),
+            threshold,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Sets the sanity bounds committed updates are held to; updates outside
+    /// them are rejected. Zero (or false) switches a bound off. The feed
+    /// authority sets them until it hands them to a separate limits authority
+    /// (e.g. the platform), which from then on is the only one that can change
+    /// them, so the wallet pushing updates can't loosen its own bounds.
+    pub fn set_feed_limits(
+        ctx: Context<SetFeedLimits>,
+        _channel_id: u32,
+        max_views_per_second: u64,
+        min_update_interval: i64,
+        taps_within_views: bool,
+        limits_authority: Pubkey,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        let current_limits_authority = if feed.limits_authority != Pubkey::default() {
+            feed.limits_authority
+        } else {
+            feed.authority
+        };
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            current_limits_authority,
+            OracleErr::BadAuthority
+        );
+        require!(min_update_interval >= 0, OracleErr::InvalidFeedLimits);
+
+        feed.max_views_per_second = max_views_per_second;
+        feed.min_update_interval = min_update_interval;
+        feed.taps_within_views = taps_within_views;
+        feed.limits_authority = limits_authority;
+
+        emit!(FeedLimitsSet {
+            channel_id: feed.channel_id,
+            max_views_per_second,
+            min_update_interval,
+            taps_within_views,
+            limits_authority,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Authority registers the keypair held by the screen. Once set, every
+    /// update must be preceded by an Ed25519 verify instruction over the
+    /// device-signed payload. The default pubkey turns the check off.
+    /// Replacing or clearing a registered key also needs the current device
+    /// key's signature, so the update authority alone can't swap it out.
+    pub fn set_device_key(
+        ctx: Context<SetDeviceKey>,
+        _channel_id: u32,
+        device_key: Pubkey,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            feed.authority,
+            OracleErr::BadAuthority
+        );
+        if feed.device_key != Pubkey::default() {
+            let current_device = ctx
+                .accounts
+                .current_device
+                .as_ref()
+                .ok_or(OracleErr::DeviceKeySignatureRequired)?;
+            require_keys_eq!(
+                current_device.key(),
+                feed.device_key,
+                OracleErr::DeviceKeySignatureRequired
+            );
+        }
+
+        let previous_device_key = feed.device_key;
+        feed.device_key = device_key;
+
+        emit!(DeviceKeySet {
+            channel_id: feed.channel_id,
+            previous_device_key,
+            device_key,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Current authority nominates a new one (e.g. when the device is sold).
+    /// Passing the default pubkey clears a pending nomination.
+    pub fn propose_feed_authority(
+        ctx: Context<ProposeFeedAuthority>,
+        _channel_id: u32,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            feed.authority,
+            OracleErr::BadAuthority
+        );
+
+        feed.pending_authority = new_authority;
+        Ok(())
+    }
+
+    /// Nominated authority takes over the feed.
+    pub fn accept_feed_authority(
+        ctx: Context<AcceptFeedAuthority>,
+        _channel_id: u32,
+    ) -> Result<()> {
+        let feed = &mut ctx.accounts.feed;
+        require!(
+            feed.pending_authority != Pubkey::default(),
+            OracleErr::NoPendingAuthority
+        );
+        require_keys_eq!(
+            ctx.accounts.new_authority.key(),
+            feed.pending_authority,
+            OracleErr::BadAuthority
+        );
+
+        let previous_authority = feed.authority;
+        feed.authority = feed.pending_authority;
+        feed.pending_authority = Pubkey::default();
+
+        emit!(FeedAuthorityTransferred {
+            channel_id: feed.channel_id,
+            previous_authority,
+            new_authority: feed.authority,
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Close a feed whose device was decommissioned, returning its rent to `receiver`.
+    pub fn close_device_feed(ctx: Context<CloseDeviceFeed>, channel_id: u32) -> Result<()> {
+        require_keys_eq!(
+            ctx.accounts.authority.key(),
+            ctx.accounts.feed.authority,
+            OracleErr::BadAuthority
+        );
+        // Otherwise the authority could close a keyed feed and re-create it
+        // without the key
+        if ctx.accounts.feed.device_key != Pubkey::default() {
+            let current_device = ctx
+                .accounts
+                .current_device
+                .as_ref()
+                .ok_or(OracleErr::DeviceKeySignatureRequired)?;
+            require_keys_eq!(
+                current_device.key(),
+                ctx.accounts.feed.device_key,
+                OracleErr::DeviceKeySignatureRequired
+            );
+        }
+        // Don't strand the history account's rent
+        require!(
+            !ctx.accounts.feed.has_history || ctx.accounts.history.is_some(),
+            OracleErr::MissingFeedHistory
+        );
+
+        emit!(DeviceFeedClosed {
+            channel_id,
+            authority: ctx.accounts.authority.key(),
+            ts: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+}
+
+/// Payload a device signs: domain | channel_id | entry_id | views | taps (LE).
+pub fn device_attestation_message(
+    channel_id: u32,
+    entry_id: u32,
+    delta_views: u64,
+    delta_taps: u64,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(DEVICE_ATTESTATION_DOMAIN.len() + 24);
+    message.extend_from_slice(DEVICE_ATTESTATION_DOMAIN);
+    message.extend_from_slice(&channel_id.to_le_bytes());
+    message.extend_from_slice(&entry_id.to_le_bytes());
+    message.extend_from_slice(&delta_views.to_le_bytes());
+    message.extend_from_slice(&delta_taps.to_le_bytes());
+    message
+}
+
+/// Check the instruction right before this one is an Ed25519 sig-verify of
+/// `message` by `device_key`. The precompile already rejected a bad signature,
+/// so only what it verified needs checking here.
+fn verify_device_signature(
+    instructions: &AccountInfo,
+    device_key: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let current_index = load_current_index_checked(instructions)?;
+    require!(current_index > 0, OracleErr::MissingDeviceSignature);
+    let ix = load_instruction_at_checked(current_index as usize - 1, instructions)?;
+    require_keys_eq!(
+        ix.program_id,
+        ed25519_program::ID,
+        OracleErr::MissingDeviceSignature
+    );
+
+    // Header: count, padding, then one 14-byte offsets record
+    let data = &ix.data;
+    require!(
+        data.len() >= 16 && data[0] == 1,
+        OracleErr::InvalidDeviceSignature
+    );
+    let read_u16 = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let public_key_offset = read_u16(6) as usize;
+    let message_offset = read_u16(10) as usize;
+    let message_size = read_u16(12) as usize;
+
+    // Signature, key and message must all live in the verify instruction itself
+    let this_instruction = u16::MAX;
+    require!(
+        read_u16(4) == this_instruction
+            && read_u16(8) == this_instruction
+            && read_u16(14) == this_instruction,
+        OracleErr::InvalidDeviceSignature
+    );
+
+    let signed_key = data
+        .get(public_key_offset..public_key_offset + 32)
+        .ok_or(OracleErr::InvalidDeviceSignature)?;
+    let signed_message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(OracleErr::InvalidDeviceSignature)?;
+    require!(
+        signed_key == device_key.as_ref() && signed_message == message,
+        OracleErr::InvalidDeviceSignature
+    );
+    Ok(())
+}
+
+/// Add a committed delta to the bucket of the hour containing `ts`,
+/// recycling the slot if it still holds an older hour.
+fn record_in_history(history: &mut FeedHistory, ts: i64, views: u64, taps: u64) -> Result<()> {
+    let hour = ts.div_euclid(SECONDS_PER_HOUR);
+    let slot = hour.rem_euclid(FEED_HISTORY_HOURS as i64) as usize;
+    let bucket = &mut history.buckets[slot];
+    if bucket.hour != hour {
+        *bucket = FeedBucket {
+            hour,
+            views: 0,
+            taps: 0,
+        };
+    }
+    bucket.views = bucket.views.checked_add(views).ok_or(OracleErr::Overflow)?;
+    bucket.taps = bucket.taps.checked_add(taps).ok_or(OracleErr::Overflow)?;
+    Ok(())
+}
+
+/// With a device key registered, the screen itself must have signed the counts.
+fn require_device_signature(
+    feed: &DeviceFeed,
+    instructions: Option<&UncheckedAccount>,
+    entry_id: u32,
+    delta_views: u64,
+    delta_taps: u64,
+) -> Result<()> {
+    if feed.device_key == Pubkey::default() {
+        return Ok(());
+    }
+    let instructions = instructions.ok_or(OracleErr::MissingDeviceSignature)?;
+    verify_device_signature(
+        instructions,
+        &feed.device_key,
+        &device_attestation_message(feed.channel_id, entry_id, delta_views, delta_taps),
+    )
+}
+
+/// Which of the feed's bounds an update breaks, if any, along with the
+/// seconds elapsed since the last committed update (or the feed's creation).
+fn feed_limit_violation(
+    feed: &DeviceFeed,
+    delta_views: u64,
+    delta_taps: u64,
+    now: i64,
+) -> Option<(FeedAnomalyKind, i64)> {
+    let elapsed = now - feed.last_update_ts;
+    let allowed_views = feed
+        .max_views_per_second
+        .saturating_mul(elapsed.max(0) as u64);
+
+    let kind = if elapsed < feed.min_update_interval {
+        FeedAnomalyKind::UpdateTooFrequent
+    } else if feed.max_views_per_second > 0 && delta_views > allowed_views {
+        FeedAnomalyKind::ViewRateExceeded
+    } else if feed.taps_within_views && delta_taps > delta_views {
+        FeedAnomalyKind::TapsExceedViews
+    } else {
+        return None;
+    };
+    Some((kind, elapsed))
+}
+
+fn clear_pending_update(feed: &mut DeviceFeed) {
+    feed.pending_updates.clear();
 }
 
 /* --------------------------- Accounts ----------------------------------- */
 
 #[account]
+#[derive(InitSpace)]
 pub struct DeviceFeed {
     pub channel_id: u32,
     pub last_entry_id: u32,
     pub total_views: u64,
     pub total_taps: u64,
-    pub last_update_ts: i64,
+    pub last_update_ts: i64, // last committed update, or the feed's creation
     pub authority: Pubkey,
     pub bump: u8,
+    pub pending_authority: Pubkey, // nominated by `authority`, default if none
+    #[max_len(8)] // MAX_FEED_KEEPERS
+    pub keepers: Vec<Pubkey>, // quorum members, empty when `authority` updates alone
+    pub threshold: u8,             // attestations needed to commit, 0 without keepers
+    #[max_len(8)] // one per keeper at most
+    pub pending_updates: Vec<PendingUpdate>, // candidates still collecting attestations
+    pub device_key: Pubkey, // key held by the screen, default if updates aren't device-signed
+    pub has_history: bool,  // set once a FeedHistory exists; updates must then pass it
+    pub max_views_per_second: u64, // since last_update_ts, 0 = unbounded
+    pub min_update_interval: i64, // seconds between committed updates, 0 = any
+    pub taps_within_views: bool, // reject updates with more taps than views
+    pub anomaly_count: u32, // rejected updates reported so far
+    pub last_anomaly_ts: i64, // when the last anomaly was reported, 0 if none
+    pub limits_authority: Pubkey, // sets the limits instead of `authority`, default if none
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct PendingUpdate {
+    pub entry_id: u32,
+    pub delta_views: u64,
+    pub delta_taps: u64,
+    pub attestations: u8, // bit i set once keepers[i] attested
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FeedAnomalyKind {
+    UpdateTooFrequent,
+    ViewRateExceeded,
+    TapsExceedViews,
+}
+
+impl FeedAnomalyKind {
+    fn error(self) -> OracleErr {
+        match self {
+            FeedAnomalyKind::UpdateTooFrequent => OracleErr::UpdateTooFrequent,
+            FeedAnomalyKind::ViewRateExceeded => OracleErr::ViewRateExceeded,
+            FeedAnomalyKind::TapsExceedViews => OracleErr::TapsExceedViews,
+        }
+    }
+}
+
+/// Ring of hourly counters; slot = hour % FEED_HISTORY_HOURS.
+#[account]
+#[derive(InitSpace)]
+pub struct FeedHistory {
+    pub channel_id: u32,
+    pub bump: u8,
+    pub started_at: i64,  // buckets only hold deltas committed from here on
+    pub start_views: u64, // feed totals when the history started
+    pub start_taps: u64,
+    #[max_len(168)] // FEED_HISTORY_HOURS
+    pub buckets: Vec<FeedBucket>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct FeedBucket {
+    pub hour: i64, // unix_timestamp / 3600 of the hour this slot holds
+    pub views: u64,
+    pub taps: u64,
 }
 
 #[derive(Accounts)]
@@ -94,7 +705,7 @@ pub struct InitializeDeviceFeed<'info> {
     #[account(
         init,
         payer = payer,
-        space = 8 + std::mem::size_of::<DeviceFeed>(),
+        space = 8 + DeviceFeed::INIT_SPACE,
         seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
         bump,
     )]
@@ -115,7 +726,151 @@ pub struct UpdateDeviceFeed<'info> {
         bump = feed.bump,
     )]
     pub feed: Account<'info, DeviceFeed>,
-    pub signer: Signer<'info>, // must match feed.authority
+    pub signer: Signer<'info>, // feed.authority, or one of feed.keepers with a quorum set
+    #[account(
+        mut,
+        seeds = [FEED_HISTORY_SEED, &channel_id.to_le_bytes()],
+        bump = history.bump,
+    )]
+    pub history: Option<Account<'info, FeedHistory>>, // required once feed.has_history
+    /// CHECK: instructions sysvar, needed once the feed has a device key
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct ReportFeedAnomaly<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    pub signer: Signer<'info>, // feed.authority or one of feed.keepers
+    /// CHECK: instructions sysvar, needed once the feed has a device key
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct InitializeFeedHistory<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FeedHistory::INIT_SPACE,
+        seeds = [FEED_HISTORY_SEED, &channel_id.to_le_bytes()],
+        bump,
+    )]
+    pub history: Account<'info, FeedHistory>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub authority: Signer<'info>, // must match feed.authority
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct SetFeedLimits<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    pub authority: Signer<'info>, // feed.limits_authority, or feed.authority while none is set
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct SetDeviceKey<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    pub authority: Signer<'info>, // must match feed.authority
+    pub current_device: Option<Signer<'info>>, // must match feed.device_key once one is set
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct SetFeedAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    pub authority: Signer<'info>, // must match feed.authority
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct SetFeedKeepers<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    pub authority: Signer<'info>, // must match feed.authority
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct ProposeFeedAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    pub authority: Signer<'info>, // must match feed.authority
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct AcceptFeedAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    pub new_authority: Signer<'info>, // must match feed.pending_authority
+}
+
+#[derive(Accounts)]
+#[instruction(channel_id: u32)]
+pub struct CloseDeviceFeed<'info> {
+    #[account(
+        mut,
+        seeds = [DEVICE_FEED_SEED, &channel_id.to_le_bytes()],
+        bump = feed.bump,
+        close = receiver,
+    )]
+    pub feed: Account<'info, DeviceFeed>,
+    #[account(
+        mut,
+        seeds = [FEED_HISTORY_SEED, &channel_id.to_le_bytes()],
+        bump = history.bump,
+        close = receiver,
+    )]
+    pub history: Option<Account<'info, FeedHistory>>, // closed with the feed if it exists
+    pub authority: Signer<'info>, // must match feed.authority
+    pub current_device: Option<Signer<'info>>, // must match feed.device_key if one is set
+    /// CHECK: only receives the feed's rent
+    #[account(mut)]
+    pub receiver: UncheckedAccount<'info>,
 }
 
 /* ----------------------------- Errors ----------------------------------- */
@@ -127,6 +882,37 @@ pub enum OracleErr {
     NoNewData,
     #[msg("Math overflow")]
     Overflow,
+    #[msg("No authority has been nominated")]
+    NoPendingAuthority,
+    #[msg("Too many keepers")]
+    TooManyKeepers,
+    #[msg("Keeper listed twice")]
+    DuplicateKeeper,
+    #[msg("Threshold must be between 1 and the number of keepers")]
+    InvalidThreshold,
+    // No longer raised (keepers may back different candidates); kept so later codes keep their numbers
+    #[msg("Attestation differs from the pending update")]
+    AttestationMismatch,
+    #[msg("Keeper already attested to this or a newer update")]
+    AlreadyAttested,
+    #[msg("Update must follow an Ed25519 verify of the device's signature")]
+    MissingDeviceSignature,
+    #[msg("Device signature does not cover this update")]
+    InvalidDeviceSignature,
+    #[msg("Feed keeps history; pass its history account")]
+    MissingFeedHistory,
+    #[msg("Feed limits must not be negative")]
+    InvalidFeedLimits,
+    #[msg("Update arrived before the feed's minimum interval")]
+    UpdateTooFrequent,
+    #[msg("Changing or closing a keyed feed needs the device key's signature")]
+    DeviceKeySignatureRequired,
+    #[msg("More views than the feed allows for the time elapsed")]
+    ViewRateExceeded,
+    #[msg("Update reports more taps than views")]
+    TapsExceedViews,
+    #[msg("Update is within the feed's limits")]
+    WithinFeedLimits,
 }
 
 #[event] // NEW
@@ -148,3 +934,70 @@ pub struct DeviceFeedUpdated {
     pub total_taps: u64,   // NEW
     pub ts: i64,           // NEW
 }
+
+#[event]
+pub struct FeedAuthorityTransferred {
+    pub channel_id: u32,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct DeviceKeySet {
+    pub channel_id: u32,
+    pub previous_device_key: Pubkey,
+    pub device_key: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct FeedKeepersSet {
+    pub channel_id: u32,
+    pub keepers: Vec<Pubkey>,
+    pub threshold: u8,
+    pub ts: i64,
+}
+
+#[event]
+pub struct FeedUpdateAttested {
+    pub channel_id: u32,
+    pub keeper: Pubkey,
+    pub entry_id: u32,
+    pub attestations: u8,
+    pub threshold: u8,
+}
+
+#[event]
+pub struct DeviceFeedClosed {
+    pub channel_id: u32,
+    pub authority: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct FeedHistoryInitialized {
+    pub channel_id: u32,
+    pub ts: i64,
+}
+
+#[event]
+pub struct FeedLimitsSet {
+    pub channel_id: u32,
+    pub max_views_per_second: u64,
+    pub min_update_interval: i64,
+    pub taps_within_views: bool,
+    pub limits_authority: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct FeedAnomaly {
+    pub channel_id: u32,
+    pub signer: Pubkey,
+    pub entry_id: u32,
+    pub delta_views: u64,
+    pub delta_taps: u64,
+    pub elapsed: i64, // seconds since last_update_ts
+    pub kind: FeedAnomalyKind,
+}
@@ -1,11 +1,8 @@
 // Anchor discriminator size (8 bytes)
 pub const ANCHOR_DISCRIMINATOR_SIZE: usize = 8;
 
-// Maximum number of providers in the global registry
-pub const MAX_PROVIDERS_IN_REGISTRY: usize = 50;
-
-// Maximum number of devices per provider
-pub const MAX_DEVICES_PER_PROVIDER: usize = 10;
+// Maximum number of providers listed on a single registry page
+pub const MAX_PROVIDERS_PER_REGISTRY_PAGE: usize = 50;
 
 // Maximum number of providers per campaign
 pub const MAX_PROVIDERS_PER_CAMPAIGN: usize = 20;
@@ -13,12 +10,35 @@ pub const MAX_PROVIDERS_PER_CAMPAIGN: usize = 20;
 // Maximum number of locations per campaign
 pub const MAX_LOCATIONS_PER_CAMPAIGN: usize = 20;
 
-// Platform fee percentage (2%)
-pub const PLATFORM_FEE_PERCENTAGE: u64 = 2;
+// Default platform fee in basis points (2%)
+pub const DEFAULT_PLATFORM_FEE_BPS: u16 = 200;
+
+// Upper bound for any platform fee in basis points (20%)
+pub const MAX_PLATFORM_FEE_BPS: u16 = 2_000;
+
+// Basis points in 100%
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// Time providers have to withdraw before leftover budget can be reclaimed (30 days)
+pub const CLAIM_WINDOW_SECONDS: i64 = 30 * 24 * 60 * 60;
 
 // String length constants
 pub const MAX_NAME_LENGTH: usize = 32;
 pub const MAX_LOCATION_LENGTH: usize = 64;
 pub const MAX_EMAIL_LENGTH: usize = 32;
 pub const MAX_CAMPAIGN_NAME_LENGTH: usize = 20;
-pub const MAX_CAMPAIGN_DESCRIPTION_LENGTH: usize = 100;
\ No newline at end of file
+pub const MAX_CAMPAIGN_DESCRIPTION_LENGTH: usize = 100;
+// Provider reputation
+pub const DEFAULT_PROVIDER_RATING: u8 = 50;
+pub const MAX_RATING: u8 = 100;
+// Relative weight of each signal in the rating
+pub const REVIEW_RATING_WEIGHT: u64 = 50;
+pub const UPTIME_RATING_WEIGHT: u64 = 25;
+pub const BOOKING_RATING_WEIGHT: u64 = 25;
+// Width of one oracle feed history bucket (1 hour)
+pub const SECONDS_PER_HOUR: i64 = 60 * 60;
+// Hours the oracle's feed history ring holds (one week)
+pub const FEED_HISTORY_HOURS: i64 = 168;
+// Limits for a campaign's daily running hours
+pub const HOURS_PER_DAY: u32 = 24;
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
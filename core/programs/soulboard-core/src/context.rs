@@ -1,9 +1,10 @@
 use crate::constants::*;
 use crate::states::*;
 use anchor_lang::prelude::*;    
+use anchor_spl::token::{Mint, Token, TokenAccount};
 
 declare_program!(oracle);
-use oracle::accounts::DeviceFeed;
+use oracle::accounts::{DeviceFeed, FeedHistory};
 
 
 
@@ -25,7 +26,61 @@ pub struct InitializeRegistry<'info> {
     pub system_program: Program<'info, System>,
 }
 
+// Initialize the global platform config (should be called once)
 #[derive(Accounts)]
+pub struct InitializePlatformConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = ANCHOR_DISCRIMINATOR_SIZE + PlatformConfig::INIT_SPACE,
+        seeds = [b"platform_config"],
+        bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Context for the admin updating platform settings
+#[derive(Accounts)]
+pub struct UpdatePlatformConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+}
+
+// Context for the admin setting a per-campaign platform fee
+#[derive(Accounts)]
+#[instruction(campaign_id: u32)]
+pub struct SetCampaignFeeOverride<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign".as_ref(), campaign.authority.as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+}
+
+#[derive(Accounts)]
+#[instruction(page_index: u32)]
 pub struct RegisterProvider<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
@@ -41,143 +96,836 @@ pub struct RegisterProvider<'info> {
 
     #[account(
         mut,
-        seeds = [b"provider_registry"],
+        seeds = [b"provider_registry"],
+        bump
+    )]
+    pub provider_registry: Account<'info, ProviderRegistry>,
+
+    // Page the provider is listed on, created when registration opens a new page
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR_SIZE + RegistryPage::INIT_SPACE,
+        seeds = [b"registry_page".as_ref(), &page_index.to_le_bytes()],
+        bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR_SIZE + ProviderMetadata::INIT_SPACE,
+        seeds = [b"provider_metadata".as_ref(), authority.key().as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Context for a provider ordering a device. The device account is created in
+// the Ordered state and the hardware payment is held in the order account.
+#[derive(Accounts)]
+#[instruction(device_id: u32)]
+pub struct OrderDevice<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR_SIZE + Device::INIT_SPACE,
+        seeds = [b"device".as_ref(), ad_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump
+    )]
+    pub device: Account<'info, Device>,
+
+    // Claims the device id globally; init fails if another provider already owns it
+    #[account(
+        init,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR_SIZE + DeviceIndex::INIT_SPACE,
+        seeds = [b"device_index".as_ref(), &device_id.to_le_bytes()],
+        bump
+    )]
+    pub device_index: Account<'info, DeviceIndex>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR_SIZE + DeviceOrder::INIT_SPACE,
+        seeds = [b"device_order".as_ref(), ad_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump
+    )]
+    pub device_order: Account<'info, DeviceOrder>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), ad_provider.provider_key.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Context for the platform fulfilment key confirming a device was delivered
+#[derive(Accounts)]
+#[instruction(device_id: u32)]
+pub struct MarkOrderDelivered<'info> {
+    pub fulfilment_authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = fulfilment_authority,
+        has_one = treasury
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    /// CHECK: Receives the hardware payment; pinned to platform_config.treasury
+    #[account(mut)]
+    pub treasury: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"device_order".as_ref(), device_order.authority.as_ref(), &device_id.to_le_bytes()],
+        bump = device_order.bump
+    )]
+    pub device_order: Account<'info, DeviceOrder>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), device_order.authority.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), device_order.authority.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+}
+
+// Context for a provider cancelling an undelivered order. The order, device and
+// device index are closed, refunding the payment and releasing the device id.
+#[derive(Accounts)]
+#[instruction(device_id: u32)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"device_order".as_ref(), ad_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump = device_order.bump,
+        close = authority
+    )]
+    pub device_order: Account<'info, DeviceOrder>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), ad_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump,
+        close = authority
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"device_index".as_ref(), &device_id.to_le_bytes()],
+        bump = device_index.bump,
+        close = authority
+    )]
+    pub device_index: Account<'info, DeviceIndex>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), ad_provider.provider_key.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Context for a provider retiring an idle device. The device, its index entry
+// and its order record are closed; passing the oracle accounts also closes the feed.
+#[derive(Accounts)]
+#[instruction(device_id: u32)]
+pub struct DecommissionDevice<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), ad_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump,
+        close = authority
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"device_index".as_ref(), &device_id.to_le_bytes()],
+        bump = device_index.bump,
+        close = authority
+    )]
+    pub device_index: Account<'info, DeviceIndex>,
+
+    /// CHECK: Order record at its PDA; closed if it exists, as a device that
+    /// changed hands no longer has one
+    #[account(
+        mut,
+        seeds = [b"device_order".as_ref(), ad_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump
+    )]
+    pub device_order: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), ad_provider.provider_key.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    // Oracle feed to close alongside the device, if it has one
+    #[account(
+        mut,
+        seeds = [b"device_feed", &device_id.to_le_bytes()],
+        bump = device_feed.bump,
+        seeds::program = oracle::ID,
+    )]
+    pub device_feed: Option<Account<'info, DeviceFeed>>,
+
+    // Closed along with the feed when it keeps history
+    #[account(
+        mut,
+        seeds = [b"feed_history", &device_id.to_le_bytes()],
+        bump = feed_history.bump,
+        seeds::program = oracle::ID,
+    )]
+    pub feed_history: Option<Account<'info, FeedHistory>>,
+
+    // The device's own key co-signs closing a feed that has one registered
+    pub current_device: Option<Signer<'info>>,
+
+    pub oracle_program: Option<Program<'info, oracle::program::Oracle>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// Context for an owner proposing to hand a device to another registered provider
+#[derive(Accounts)]
+#[instruction(device_id: u32, new_owner: Pubkey)]
+pub struct ProposeDeviceTransfer<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), ad_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump
+    )]
+    pub device: Account<'info, Device>,
+
+    // The receiving provider must already be registered
+    #[account(
+        seeds = [b"ad_provider".as_ref(), new_owner.as_ref()],
+        bump
+    )]
+    pub new_owner_provider: Account<'info, AdProvider>,
+
+    // Oracle feed whose authority should follow the device, if it has one
+    #[account(
+        mut,
+        seeds = [b"device_feed", &device_id.to_le_bytes()],
+        bump = device_feed.bump,
+        seeds::program = oracle::ID,
+    )]
+    pub device_feed: Option<Account<'info, DeviceFeed>>,
+
+    /// CHECK: Program signer nominated on the feed until the transfer is accepted
+    #[account(seeds = [b"feed_transfer".as_ref()], bump)]
+    pub feed_transfer_authority: UncheckedAccount<'info>,
+
+    pub oracle_program: Option<Program<'info, oracle::program::Oracle>>,
+}
+
+// Context for an owner withdrawing a pending transfer proposal
+#[derive(Accounts)]
+#[instruction(device_id: u32)]
+pub struct CancelDeviceTransfer<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), ad_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"device_feed", &device_id.to_le_bytes()],
+        bump = device_feed.bump,
+        seeds::program = oracle::ID,
+    )]
+    pub device_feed: Option<Account<'info, DeviceFeed>>,
+
+    pub oracle_program: Option<Program<'info, oracle::program::Oracle>>,
+}
+
+// Context for the receiving provider accepting a device. The device is re-created
+// under the new owner's seeds and the previous owner's device and order record are
+// closed back to them.
+#[derive(Accounts)]
+#[instruction(device_id: u32)]
+pub struct AcceptDeviceTransfer<'info> {
+    #[account(mut)]
+    pub new_owner: Signer<'info>,
+
+    #[account(
+        seeds = [b"ad_provider".as_ref(), new_owner_provider.provider_key.as_ref()],
+        bump,
+        constraint = new_owner_provider.authority == new_owner.key() @ crate::ErrorCode::NotProviderAuthority
+    )]
+    pub new_owner_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), device.authority.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump,
+        close = previous_owner
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        seeds = [b"ad_provider".as_ref(), device.authority.as_ref()],
+        bump
+    )]
+    pub previous_owner_provider: Account<'info, AdProvider>,
+
+    /// CHECK: Receives the rent of the closed accounts; pinned to the previous provider's wallet
+    #[account(mut, address = previous_owner_provider.authority)]
+    pub previous_owner: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = new_owner,
+        space = ANCHOR_DISCRIMINATOR_SIZE + Device::INIT_SPACE,
+        seeds = [b"device".as_ref(), new_owner_provider.provider_key.as_ref(), &device_id.to_le_bytes()],
+        bump
+    )]
+    pub new_device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"device_index".as_ref(), &device_id.to_le_bytes()],
+        bump = device_index.bump
+    )]
+    pub device_index: Account<'info, DeviceIndex>,
+
+    /// CHECK: The buyer's order record at its PDA; the purchase stays with the
+    /// buyer and is settled here if the record exists
+    #[account(
+        mut,
+        seeds = [b"device_order".as_ref(), device.authority.as_ref(), &device_id.to_le_bytes()],
+        bump
+    )]
+    pub device_order: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), device.authority.as_ref()],
+        bump
+    )]
+    pub previous_owner_metadata: Account<'info, ProviderMetadata>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), new_owner_provider.provider_key.as_ref()],
+        bump
+    )]
+    pub new_owner_metadata: Account<'info, ProviderMetadata>,
+
+    /// CHECK: The device's oracle feed PDA; moved to new_owner if the proposal
+    /// nominated the transfer signer for it
+    #[account(
+        mut,
+        seeds = [b"device_feed", &device_id.to_le_bytes()],
+        bump,
+        seeds::program = oracle::ID,
+    )]
+    pub device_feed: UncheckedAccount<'info>,
+
+    /// CHECK: Program signer holding the feed since the proposal; hands it to new_owner
+    #[account(seeds = [b"feed_transfer".as_ref()], bump)]
+    pub feed_transfer_authority: UncheckedAccount<'info>,
+
+    pub oracle_program: Option<Program<'info, oracle::program::Oracle>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u32)]
+pub struct CreateCampaign<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR_SIZE + Campaign::INIT_SPACE,
+        seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR_SIZE + CampaignVault::INIT_SPACE,
+        seeds = [b"campaign_vault".as_ref(), campaign.key().as_ref()],
+        bump
+    )]
+    pub campaign_vault: Account<'info, CampaignVault>,
+
+    // SPL budget accounts, only passed when the campaign is funded in a token
+    pub budget_mint: Option<Account<'info, Mint>>,
+
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"campaign_token_account".as_ref(), campaign.key().as_ref()],
+        bump,
+        token::mint = budget_mint,
+        token::authority = campaign_vault,
+    )]
+    pub campaign_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u32)]
+pub struct AddBudget<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign_vault".as_ref(), campaign.key().as_ref()],
+        bump = campaign_vault.bump
+    )]
+    pub campaign_vault: Account<'info, CampaignVault>,
+
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    // SPL budget accounts, required when campaign.budget_mint is set
+    #[account(mut, token::authority = authority)]
+    pub authority_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign_token_account".as_ref(), campaign.key().as_ref()],
+        bump
+    )]
+    pub campaign_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u32, location: Pubkey, device_id: u32)]
+pub struct AddLocation<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), location.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), location.as_ref()],
+        bump
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), location.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    // Feed counts at booking become the baseline; zero until the feed exists
+    /// CHECK: the device's oracle feed PDA, read only if initialized
+    #[account(
+        seeds = [b"device_feed", &device_id.to_le_bytes()],
+        bump,
+        seeds::program = oracle::ID,
+    )]
+    pub device_feed: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u32, location: Pubkey, device_id: u32)]
+pub struct RemoveLocation<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), location.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), location.as_ref()],
+        bump
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), location.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// New context for updating campaign performance from oracle
+#[derive(Accounts)]
+#[instruction(campaign_id: u32, device_id: u32)]
+pub struct UpdateCampaignPerformance<'info> {
+    // Anyone may crank; the counts come from the oracle either way
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign".as_ref(), campaign.authority.as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+
+    #[account(
+        seeds = [b"device_feed", &device_id.to_le_bytes()],
+        bump = device_feed.bump,
+        seeds::program = oracle::ID,
+    )]
+    pub device_feed: Account<'info, DeviceFeed>,
+
+    // Required once the feed keeps history; views are then counted per hour
+    #[account(
+        seeds = [b"feed_history", &device_id.to_le_bytes()],
+        bump = feed_history.bump,
+        seeds::program = oracle::ID,
+    )]
+    pub feed_history: Option<Account<'info, FeedHistory>>,
+
+    pub oracle_program: Program<'info, oracle::program::Oracle>,
+}
+
+// New context for calculating fees
+#[derive(Accounts)]
+#[instruction(campaign_id: u32)]
+pub struct CalculateFees<'info> {
+    // Anyone may settle a campaign once it has completed
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign".as_ref(), campaign.authority.as_ref(), &campaign_id.to_le_bytes()],
         bump
     )]
-    pub provider_registry: Account<'info, ProviderRegistry>,
+    pub campaign: Account<'info, Campaign>,
 
     #[account(
-        init,
-        payer = authority,
-        space = ANCHOR_DISCRIMINATOR_SIZE + ProviderMetadata::INIT_SPACE,
-        seeds = [b"provider_metadata".as_ref(), authority.key().as_ref()],
-        bump
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
     )]
-    pub provider_metadata: Account<'info, ProviderMetadata>,
+    pub platform_config: Account<'info, PlatformConfig>,
 
     pub system_program: Program<'info, System>,
 }
 
+// New context for withdrawing earnings
 #[derive(Accounts)]
-pub struct GetDevice<'info> {
+#[instruction(campaign_id: u32)]
+pub struct WithdrawEarnings<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"ad_provider".as_ref(), authority.key().as_ref()],
+        seeds = [b"campaign".as_ref(), campaign.authority.as_ref(), &campaign_id.to_le_bytes()],
         bump
     )]
+    pub campaign: Account<'info, Campaign>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        has_one = authority
+    )]
     pub ad_provider: Account<'info, AdProvider>,
 
     #[account(
         mut,
-        seeds = [b"provider_metadata".as_ref(), authority.key().as_ref()],
+        seeds = [b"campaign_vault".as_ref(), campaign.key().as_ref()],
+        bump = campaign_vault.bump
+    )]
+    pub campaign_vault: Account<'info, CampaignVault>,
+
+    // SPL payout accounts, required when campaign.budget_mint is set
+    #[account(
+        mut,
+        seeds = [b"campaign_token_account".as_ref(), campaign.key().as_ref()],
         bump
     )]
-    pub provider_metadata: Account<'info, ProviderMetadata>,
+    pub campaign_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut, token::authority = authority)]
+    pub provider_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
 
     pub system_program: Program<'info, System>,
 }
 
+// Context for moving a campaign's platform fee to the treasury
 #[derive(Accounts)]
 #[instruction(campaign_id: u32)]
-pub struct CreateCampaign<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+pub struct CollectPlatformFee<'info> {
+    pub admin: Signer<'info>,
 
     #[account(
-        init,
-        payer = authority,
-        space = ANCHOR_DISCRIMINATOR_SIZE + Campaign::INIT_SPACE,
-        seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
+        seeds = [b"platform_config"],
+        bump = platform_config.bump,
+        has_one = admin,
+        has_one = treasury
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"campaign".as_ref(), campaign.authority.as_ref(), &campaign_id.to_le_bytes()],
         bump
     )]
     pub campaign: Account<'info, Campaign>,
 
-    pub system_program: Program<'info, System>,
-}
+    #[account(
+        mut,
+        seeds = [b"campaign_vault".as_ref(), campaign.key().as_ref()],
+        bump = campaign_vault.bump
+    )]
+    pub campaign_vault: Account<'info, CampaignVault>,
 
-#[derive(Accounts)]
-#[instruction(campaign_id: u32)]
-pub struct AddBudget<'info> {
+    /// CHECK: receives SOL fees; validated against platform_config.treasury
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub treasury: UncheckedAccount<'info>,
 
+    // SPL fee accounts, required when campaign.budget_mint is set
     #[account(
         mut,
-        seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
+        seeds = [b"campaign_token_account".as_ref(), campaign.key().as_ref()],
         bump
     )]
-    pub campaign: Account<'info, Campaign>,
+    pub campaign_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut, token::authority = treasury)]
+    pub treasury_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
 
     pub system_program: Program<'info, System>,
 }
 
+// Context for the advertiser reclaiming undistributed budget. After the claim
+// window, remaining accounts carry an ad_provider per campaign_performance entry.
 #[derive(Accounts)]
-#[instruction(campaign_id: u32, location: Pubkey, device_id: u32)]
-pub struct AddLocation<'info> {
+#[instruction(campaign_id: u32)]
+pub struct RefundRemainingBudget<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
     #[account(
         mut,
         seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
-        bump
+        bump,
+        has_one = authority
     )]
     pub campaign: Account<'info, Campaign>,
 
     #[account(
         mut,
-        seeds = [b"ad_provider".as_ref(), location.as_ref()],
-        bump
+        seeds = [b"campaign_vault".as_ref(), campaign.key().as_ref()],
+        bump = campaign_vault.bump
     )]
-    pub ad_provider: Account<'info, AdProvider>,
+    pub campaign_vault: Account<'info, CampaignVault>,
 
+    // SPL refund accounts, required when campaign.budget_mint is set
     #[account(
         mut,
-        seeds = [b"provider_metadata".as_ref(), location.as_ref()],
+        seeds = [b"campaign_token_account".as_ref(), campaign.key().as_ref()],
         bump
     )]
-    pub provider_metadata: Account<'info, ProviderMetadata>,
+    pub campaign_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut, token::authority = authority)]
+    pub authority_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
 
     pub system_program: Program<'info, System>,
 }
 
+// Context for closing a fully settled campaign
 #[derive(Accounts)]
-#[instruction(campaign_id: u32, location: Pubkey, device_id: u32)]
-pub struct RemoveLocation<'info> {
+#[instruction(campaign_id: u32)]
+pub struct CloseCampaign<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
     #[account(
         mut,
         seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
-        bump
+        bump,
+        has_one = authority,
+        close = authority
     )]
     pub campaign: Account<'info, Campaign>,
 
     #[account(
         mut,
-        seeds = [b"ad_provider".as_ref(), location.as_ref()],
+        seeds = [b"campaign_vault".as_ref(), campaign.key().as_ref()],
+        bump = campaign_vault.bump,
+        close = authority
+    )]
+    pub campaign_vault: Account<'info, CampaignVault>,
+
+    // SPL accounts, required when campaign.budget_mint is set
+    #[account(
+        mut,
+        seeds = [b"campaign_token_account".as_ref(), campaign.key().as_ref()],
         bump
     )]
-    pub ad_provider: Account<'info, AdProvider>,
+    pub campaign_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut, token::authority = authority)]
+    pub authority_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+// New context for completing campaign
+#[derive(Accounts)]
+#[instruction(campaign_id: u32)]
+pub struct CompleteCampaign<'info> {
+    // Anyone may complete a campaign once its end time has passed
+    #[account(mut)]
+    pub caller: Signer<'info>,
 
     #[account(
         mut,
-        seeds = [b"provider_metadata".as_ref(), location.as_ref()],
+        seeds = [b"campaign".as_ref(), campaign.authority.as_ref(), &campaign_id.to_le_bytes()],
         bump
     )]
-    pub provider_metadata: Account<'info, ProviderMetadata>,
+    pub campaign: Account<'info, Campaign>,
 
     pub system_program: Program<'info, System>,
 }
 
-// New context for updating campaign performance from oracle
+// Context for cancelling a running campaign
 #[derive(Accounts)]
-#[instruction(campaign_id: u32, device_id: u32)]
-pub struct UpdateCampaignPerformance<'info> {
+#[instruction(campaign_id: u32)]
+pub struct CancelCampaign<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
@@ -190,19 +938,38 @@ pub struct UpdateCampaignPerformance<'info> {
     pub campaign: Account<'info, Campaign>,
 
     #[account(
-        seeds = [b"device_feed", &device_id.to_le_bytes()],
-        bump = device_feed.bump,
-        seeds::program = oracle::ID,
+        mut,
+        seeds = [b"campaign_vault".as_ref(), campaign.key().as_ref()],
+        bump = campaign_vault.bump
     )]
-    pub device_feed: Account<'info, DeviceFeed>,
+    pub campaign_vault: Account<'info, CampaignVault>,
 
-    pub oracle_program: Program<'info, oracle::program::Oracle>,
+    #[account(
+        seeds = [b"platform_config"],
+        bump = platform_config.bump
+    )]
+    pub platform_config: Account<'info, PlatformConfig>,
+
+    // SPL refund accounts, required when campaign.budget_mint is set
+    #[account(
+        mut,
+        seeds = [b"campaign_token_account".as_ref(), campaign.key().as_ref()],
+        bump
+    )]
+    pub campaign_token_account: Option<Account<'info, TokenAccount>>,
+
+    #[account(mut, token::authority = authority)]
+    pub authority_token_account: Option<Account<'info, TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+
+    pub system_program: Program<'info, System>,
 }
 
-// New context for calculating fees
+// Context for pausing a campaign
 #[derive(Accounts)]
 #[instruction(campaign_id: u32)]
-pub struct CalculateFees<'info> {
+pub struct PauseCampaign<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
@@ -213,17 +980,31 @@ pub struct CalculateFees<'info> {
         has_one = authority
     )]
     pub campaign: Account<'info, Campaign>,
-
-    pub system_program: Program<'info, System>,
 }
 
-// New context for withdrawing earnings
+// Context for resuming a paused campaign
 #[derive(Accounts)]
 #[instruction(campaign_id: u32)]
-pub struct WithdrawEarnings<'info> {
+pub struct ResumeCampaign<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
+    #[account(
+        mut,
+        seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
+        bump,
+        has_one = authority
+    )]
+    pub campaign: Account<'info, Campaign>,
+}
+
+// Context for the permissionless crank bringing one booked device in line with
+// its campaign
+#[derive(Accounts)]
+#[instruction(campaign_id: u32, device_id: u32)]
+pub struct SyncCampaignDevice<'info> {
+    pub caller: Signer<'info>,
+
     #[account(
         mut,
         seeds = [b"campaign".as_ref(), campaign.authority.as_ref(), &campaign_id.to_le_bytes()],
@@ -233,41 +1014,162 @@ pub struct WithdrawEarnings<'info> {
 
     #[account(
         mut,
-        seeds = [b"ad_provider".as_ref(), authority.key().as_ref()],
-        bump,
-        has_one = authority
+        seeds = [b"device".as_ref(), device.authority.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), device.authority.as_ref()],
+        bump
     )]
     pub ad_provider: Account<'info, AdProvider>,
 
-    pub system_program: Program<'info, System>,
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), device.authority.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
 }
 
-// New context for completing campaign
+// New context for querying one page of providers
 #[derive(Accounts)]
-#[instruction(campaign_id: u32)]
-pub struct CompleteCampaign<'info> {
+#[instruction(page_index: u32)]
+pub struct QueryProviders<'info> {
+    #[account(
+        seeds = [b"registry_page".as_ref(), &page_index.to_le_bytes()],
+        bump = registry_page.bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+}
+
+// Context for resolving a device id to its owner
+#[derive(Accounts)]
+#[instruction(device_id: u32)]
+pub struct QueryDeviceIndex<'info> {
+    #[account(
+        seeds = [b"device_index".as_ref(), &device_id.to_le_bytes()],
+        bump = device_index.bump
+    )]
+    pub device_index: Account<'info, DeviceIndex>,
+}
+
+// Context for an advertiser reviewing a provider from one of their campaigns
+#[derive(Accounts)]
+#[instruction(campaign_id: u32, provider_key: Pubkey)]
+pub struct SubmitReview<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
     #[account(
-        mut,
         seeds = [b"campaign".as_ref(), authority.key().as_ref(), &campaign_id.to_le_bytes()],
         bump,
         has_one = authority
     )]
     pub campaign: Account<'info, Campaign>,
 
+    // One review per campaign and provider; init fails on a second attempt
+    #[account(
+        init,
+        payer = authority,
+        space = ANCHOR_DISCRIMINATOR_SIZE + Review::INIT_SPACE,
+        seeds = [b"review".as_ref(), campaign.key().as_ref(), provider_key.as_ref()],
+        bump
+    )]
+    pub review: Account<'info, Review>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), provider_key.as_ref()],
+        bump
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), provider_key.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
     pub system_program: Program<'info, System>,
 }
 
-// New context for querying providers
+// Context for the permissionless uptime crank
 #[derive(Accounts)]
-pub struct QueryProviders<'info> {
+#[instruction(device_id: u32)]
+pub struct RecordDeviceUptime<'info> {
+    pub caller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"device".as_ref(), device.authority.as_ref(), &device_id.to_le_bytes()],
+        bump = device.bump
+    )]
+    pub device: Account<'info, Device>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), device.authority.as_ref()],
+        bump
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), device.authority.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    // Hourly update record of the device's oracle feed
+    #[account(
+        seeds = [b"feed_history", &device_id.to_le_bytes()],
+        bump = feed_history.bump,
+        seeds::program = oracle::ID,
+    )]
+    pub feed_history: Account<'info, FeedHistory>,
+}
+
+// Context for a provider leaving the platform. Its registry entry is removed
+// and the provider accounts are closed back to the authority.
+#[derive(Accounts)]
+pub struct DeregisterProvider<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        has_one = authority,
+        close = authority
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
     #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        close = authority
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    #[account(
+        mut,
         seeds = [b"provider_registry"],
         bump
     )]
     pub provider_registry: Account<'info, ProviderRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"registry_page".as_ref(), &ad_provider.registry_page.to_le_bytes()],
+        bump = registry_page.bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
 }
 
 // Context for updating provider information
@@ -278,7 +1180,7 @@ pub struct UpdateProvider<'info> {
 
     #[account(
         mut,
-        seeds = [b"ad_provider".as_ref(), authority.key().as_ref()],
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
         bump,
         has_one = authority
     )]
@@ -286,10 +1188,48 @@ pub struct UpdateProvider<'info> {
 
     #[account(
         mut,
-        seeds = [b"provider_metadata".as_ref(), authority.key().as_ref()],
+        seeds = [b"provider_metadata".as_ref(), ad_provider.provider_key.as_ref()],
         bump
     )]
     pub provider_metadata: Account<'info, ProviderMetadata>,
 
     pub system_program: Program<'info, System>,
+}
+
+// Context for a provider setting or clearing its recovery key
+#[derive(Accounts)]
+pub struct SetRecoveryAuthority<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump,
+        has_one = authority
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+}
+
+// Context for moving a provider to a new wallet. Signed by the current
+// authority or the recovery key; the provider's PDAs stay where they are.
+// Remaining accounts carry the oracle feeds to move along with the wallet.
+#[derive(Accounts)]
+pub struct RotateProviderAuthority<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"ad_provider".as_ref(), ad_provider.provider_key.as_ref()],
+        bump
+    )]
+    pub ad_provider: Account<'info, AdProvider>,
+
+    #[account(
+        mut,
+        seeds = [b"provider_metadata".as_ref(), ad_provider.provider_key.as_ref()],
+        bump
+    )]
+    pub provider_metadata: Account<'info, ProviderMetadata>,
+
+    pub oracle_program: Option<Program<'info, oracle::program::Oracle>>,
 }
\ No newline at end of file
@@ -1,10 +1,13 @@
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
+use anchor_spl::token::{self, Token, TokenAccount};
 pub mod constants;
 pub mod context;
 pub mod states;
+use constants::*;
 use context::*;
 use states::*;
+use oracle::accounts::DeviceFeed;
 
 declare_id!("Gt2gt87crJPt9Y3FbnVkAfdwxQ9cVVTE8mhAH6PKVHm7");
 
@@ -17,7 +20,7 @@ pub mod soulboard_core {
     pub fn initialize_registry(ctx: Context<InitializeRegistry>) -> Result<()> {
         let registry = &mut ctx.accounts.provider_registry;
         registry.total_providers = 0;
-        registry.providers = Vec::new();
+        registry.page_count = 0;
 
         emit!(RegistryInitialized {
             registry: ctx.accounts.provider_registry.key(),
@@ -25,37 +28,166 @@ pub mod soulboard_core {
         Ok(())
     }
 
-    // Register a provider and add to global registry
+    // Initialize the global platform config (call this once when deploying)
+    pub fn initialize_platform_config(
+        ctx: Context<InitializePlatformConfig>,
+        treasury: Pubkey,
+        platform_fee_bps: u16,
+        fulfilment_authority: Pubkey,
+        device_price: u64,
+    ) -> Result<()> {
+        require!(
+            platform_fee_bps <= MAX_PLATFORM_FEE_BPS,
+            ErrorCode::InvalidPlatformFee
+        );
+
+        let platform_config = &mut ctx.accounts.platform_config;
+        platform_config.admin = ctx.accounts.admin.key();
+        platform_config.treasury = treasury;
+        platform_config.platform_fee_bps = platform_fee_bps;
+        platform_config.fulfilment_authority = fulfilment_authority;
+        platform_config.device_price = device_price;
+        platform_config.bump = ctx.bumps.platform_config;
+
+        emit!(PlatformConfigInitialized {
+            admin: platform_config.admin,
+            treasury,
+            fulfilment_authority,
+        });
+        Ok(())
+    }
+
+    // Update platform settings (admin only)
+    pub fn update_platform_config(
+        ctx: Context<UpdatePlatformConfig>,
+        new_admin: Option<Pubkey>,
+        treasury: Option<Pubkey>,
+        platform_fee_bps: Option<u16>,
+        fulfilment_authority: Option<Pubkey>,
+        device_price: Option<u64>,
+    ) -> Result<()> {
+        let platform_config = &mut ctx.accounts.platform_config;
+
+        if let Some(admin) = new_admin {
+            platform_config.admin = admin;
+        }
+
+        if let Some(treasury) = treasury {
+            platform_config.treasury = treasury;
+        }
+
+        if let Some(fee_bps) = platform_fee_bps {
+            require!(fee_bps <= MAX_PLATFORM_FEE_BPS, ErrorCode::InvalidPlatformFee);
+            platform_config.platform_fee_bps = fee_bps;
+        }
+
+        if let Some(fulfilment_authority) = fulfilment_authority {
+            platform_config.fulfilment_authority = fulfilment_authority;
+        }
+
+        if let Some(device_price) = device_price {
+            platform_config.device_price = device_price;
+        }
+
+        emit!(PlatformConfigUpdated {
+            admin: platform_config.admin,
+            treasury: platform_config.treasury,
+            platform_fee_bps: platform_config.platform_fee_bps,
+            fulfilment_authority: platform_config.fulfilment_authority,
+            device_price: platform_config.device_price,
+        });
+        Ok(())
+    }
+
+    // Override the platform fee for a single campaign (admin only); None restores the default
+    pub fn set_campaign_fee_override(
+        ctx: Context<SetCampaignFeeOverride>,
+        _campaign_id: u32,
+        platform_fee_bps: Option<u16>,
+    ) -> Result<()> {
+        let campaign = &mut ctx.accounts.campaign;
+
+        require!(!campaign.fees_calculated, ErrorCode::FeesAlreadyCalculated);
+        if let Some(fee_bps) = platform_fee_bps {
+            require!(fee_bps <= MAX_PLATFORM_FEE_BPS, ErrorCode::InvalidPlatformFee);
+        }
+
+        campaign.platform_fee_bps_override = platform_fee_bps;
+
+        // Keep the budget fee in line with the new rate
+        let fee_bps = platform_fee_bps.unwrap_or(ctx.accounts.platform_config.platform_fee_bps);
+        campaign.platform_fee = calculate_platform_fee(campaign.campaign_budget, fee_bps)?;
+
+        emit!(CampaignFeeOverrideSet {
+            campaign: campaign.key(),
+            platform_fee_bps_override: platform_fee_bps,
+        });
+        Ok(())
+    }
+
+    // Register a provider and add to global registry. `page_index` is either an
+    // existing page with room or `page_count` to open the next page.
     pub fn register_provider(
         ctx: Context<RegisterProvider>,
+        page_index: u32,
         name: String,
         location: String,
         contact_email: String,
     ) -> Result<()> {
+        validate_text(&name, MAX_NAME_LENGTH, ErrorCode::NameEmpty, ErrorCode::NameTooLong)?;
+        validate_text(
+            &location,
+            MAX_LOCATION_LENGTH,
+            ErrorCode::LocationEmpty,
+            ErrorCode::LocationTooLong,
+        )?;
+        validate_email(&contact_email)?;
+
         let ad_provider = &mut ctx.accounts.ad_provider;
         let registry = &mut ctx.accounts.provider_registry;
+        let registry_page = &mut ctx.accounts.registry_page;
         let metadata = &mut ctx.accounts.provider_metadata;
 
-        // Check if registry has space for another provider
+        // Pages are created in order, so the only new page allowed is the next one
+        require!(
+            page_index <= registry.page_count,
+            ErrorCode::InvalidRegistryPage
+        );
+        if page_index == registry.page_count {
+            registry_page.page_index = page_index;
+            registry_page.providers = Vec::new();
+            registry_page.bump = ctx.bumps.registry_page;
+            registry.page_count += 1;
+        }
+
+        // Check if the page has space for another provider
         require!(
-            registry.providers.len() < 50, // MAX_PROVIDERS_IN_REGISTRY
+            registry_page.providers.len() < MAX_PROVIDERS_PER_REGISTRY_PAGE,
             ErrorCode::RegistryFull
         );
 
         // Initialize provider account
         ad_provider.authority = ctx.accounts.authority.key();
-        ad_provider.devices = Vec::new();
         ad_provider.name = name.clone();
         ad_provider.location = location.clone();
         ad_provider.contact_email = contact_email;
-        ad_provider.rating = 50; // Default rating
+        ad_provider.rating = DEFAULT_PROVIDER_RATING;
         ad_provider.total_campaigns = 0;
         ad_provider.is_active = true;
         ad_provider.total_earnings = 0;
         ad_provider.pending_payments = 0;
+        ad_provider.registry_page = page_index;
+        ad_provider.provider_key = ctx.accounts.authority.key();
+        ad_provider.recovery_authority = None;
+        ad_provider.review_count = 0;
+        ad_provider.review_score_total = 0;
+        ad_provider.uptime_checks = 0;
+        ad_provider.uptime_hits = 0;
+        ad_provider.fulfilled_bookings = 0;
+        ad_provider.cancelled_bookings = 0;
 
         // Add to global registry
-        registry.providers.push(ctx.accounts.authority.key());
+        registry_page.providers.push(ctx.accounts.authority.key());
         registry.total_providers += 1;
 
         // Initialize metadata for faster querying
@@ -65,34 +197,215 @@ pub mod soulboard_core {
         metadata.location = location.clone();
         metadata.device_count = 0;
         metadata.available_devices = 0;
-        metadata.rating = 50;
+        metadata.rating = DEFAULT_PROVIDER_RATING;
         metadata.is_active = true;
+        metadata.provider_key = ctx.accounts.authority.key();
 
         emit!(ProviderRegistered {
             authority: ctx.accounts.authority.key(),
             name,
             location,
+            registry_page: page_index,
         });
         Ok(())
     }
 
-    // Add a device and update metadata
-    pub fn get_device(ctx: Context<GetDevice>, device_id: u32) -> Result<()> {
-        let ad_provider = &mut ctx.accounts.ad_provider;
+    // Order a device. It stays in the Ordered state until the platform confirms delivery.
+    pub fn order_device(
+        ctx: Context<OrderDevice>,
+        device_id: u32,
+        location: String,
+        specs: DeviceSpecs,
+    ) -> Result<()> {
+        validate_text(
+            &location,
+            MAX_LOCATION_LENGTH,
+            ErrorCode::LocationEmpty,
+            ErrorCode::LocationTooLong,
+        )?;
+
+        let device = &mut ctx.accounts.device;
         let metadata = &mut ctx.accounts.provider_metadata;
 
-        ad_provider.devices.push(Soulboard {
-            device_id,
-            device_state: DeviceState::Available,
-        });
+        let provider_key = ctx.accounts.ad_provider.provider_key;
+        device.authority = provider_key;
+        device.provider = ctx.accounts.ad_provider.key();
+        device.device_id = device_id;
+        device.device_state = DeviceState::Ordered;
+        device.location = location;
+        device.specs = specs;
+        device.current_campaign = None;
+        device.pending_owner = None;
+        device.last_uptime_check = 0;
+        device.bump = ctx.bumps.device;
+
+        // Record the owner of this device id
+        let device_index = &mut ctx.accounts.device_index;
+        device_index.device_id = device_id;
+        device_index.authority = provider_key;
+        device_index.ad_provider = ctx.accounts.ad_provider.key();
+        device_index.device = device.key();
+        device_index.bump = ctx.bumps.device_index;
+
+        // Hold the hardware payment in the order account until delivery
+        let device_price = ctx.accounts.platform_config.device_price;
+        if device_price > 0 {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.device_order.to_account_info(),
+                },
+            );
+            system_program::transfer(cpi_context, device_price)?;
+        }
+
+        let device_order = &mut ctx.accounts.device_order;
+        device_order.authority = provider_key;
+        device_order.device_id = device_id;
+        device_order.order_status = OrderStatus::Ordered;
+        device_order.amount_paid = device_price;
+        device_order.ordered_at = Clock::get()?.unix_timestamp;
+        device_order.delivered_at = 0;
+        device_order.bump = ctx.bumps.device_order;
 
         // Update metadata
         metadata.device_count += 1;
-        metadata.available_devices += 1;
 
         emit!(DeviceOrdered {
             device_id,
-            device_state: DeviceState::Available,
+            device_state: DeviceState::Ordered,
+        });
+
+        Ok(())
+    }
+
+    // Confirm an order was delivered (fulfilment key only), making the device available
+    pub fn mark_order_delivered(ctx: Context<MarkOrderDelivered>, device_id: u32) -> Result<()> {
+        let device_order = &mut ctx.accounts.device_order;
+        let device = &mut ctx.accounts.device;
+        let metadata = &mut ctx.accounts.provider_metadata;
+
+        require!(
+            device_order.order_status == OrderStatus::Ordered,
+            ErrorCode::OrderNotPending
+        );
+
+        // Release the hardware payment to the treasury
+        let amount_paid = device_order.amount_paid;
+        if amount_paid > 0 {
+            **device_order.to_account_info().try_borrow_mut_lamports()? -= amount_paid;
+            **ctx.accounts.treasury.to_account_info().try_borrow_mut_lamports()? += amount_paid;
+        }
+
+        device_order.order_status = OrderStatus::Delivered;
+        device_order.delivered_at = Clock::get()?.unix_timestamp;
+        device.device_state = DeviceState::Available;
+        metadata.available_devices += 1;
+
+        emit!(DeviceDelivered {
+            device_id,
+            authority: device_order.authority,
+            amount_paid,
+        });
+
+        emit!(ProviderMetadataUpdated {
+            authority: device_order.authority,
+            available_devices: metadata.available_devices,
+        });
+
+        Ok(())
+    }
+
+    // Cancel an undelivered order; closing the accounts refunds the payment and rent
+    pub fn cancel_order(ctx: Context<CancelOrder>, device_id: u32) -> Result<()> {
+        let device_order = &ctx.accounts.device_order;
+        let metadata = &mut ctx.accounts.provider_metadata;
+
+        require!(
+            device_order.order_status == OrderStatus::Ordered,
+            ErrorCode::OrderNotPending
+        );
+
+        metadata.device_count = metadata
+            .device_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        emit!(DeviceOrderCancelled {
+            device_id,
+            authority: device_order.authority,
+            amount_refunded: device_order.amount_paid,
+        });
+
+        Ok(())
+    }
+
+    // Retire an idle device along with its order record, optionally closing its oracle feed as well
+    pub fn decommission_device(ctx: Context<DecommissionDevice>, device_id: u32) -> Result<()> {
+        let device = &ctx.accounts.device;
+        let metadata = &mut ctx.accounts.provider_metadata;
+
+        // Booked or paused devices still owe a campaign their screen time
+        require!(
+            device.device_state == DeviceState::Available,
+            ErrorCode::DeviceNotAvailable
+        );
+
+        let feed_closed = match oracle_accounts(
+            ctx.accounts.device_feed.as_ref(),
+            ctx.accounts.oracle_program.as_ref(),
+        )? {
+            Some((device_feed, oracle_program)) => {
+                let cpi_context = CpiContext::new(
+                    oracle_program.to_account_info(),
+                    oracle::cpi::accounts::CloseDeviceFeed {
+                        feed: device_feed.to_account_info(),
+                        history: ctx
+                            .accounts
+                            .feed_history
+                            .as_ref()
+                            .map(|history| history.to_account_info()),
+                        authority: ctx.accounts.authority.to_account_info(),
+                        current_device: ctx
+                            .accounts
+                            .current_device
+                            .as_ref()
+                            .map(|device| device.to_account_info()),
+                        receiver: ctx.accounts.authority.to_account_info(),
+                    },
+                );
+                oracle::cpi::close_device_feed(cpi_context, device_id)?;
+                true
+            }
+            None => false,
+        };
+
+        // A leftover order record would block ordering the same device id again
+        let device_order = ctx.accounts.device_order.to_account_info();
+        if device_order.owner == &crate::ID {
+            DeviceOrder::try_deserialize(&mut &device_order.try_borrow_data()?[..])?;
+
+            let rent = device_order.lamports();
+            **device_order.try_borrow_mut_lamports()? -= rent;
+            **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? += rent;
+            device_order.assign(&system_program::ID);
+            device_order.realloc(0, false)?;
+        }
+
+        metadata.device_count = metadata
+            .device_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::CalculationError)?;
+        metadata.available_devices = metadata
+            .available_devices
+            .checked_sub(1)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        emit!(DeviceDecommissioned {
+            device_id,
+            authority: ctx.accounts.authority.key(),
+            feed_closed,
         });
 
         emit!(ProviderMetadataUpdated {
@@ -103,7 +416,201 @@ pub mod soulboard_core {
         Ok(())
     }
 
+    // Propose handing an idle device to another registered provider
+    pub fn propose_device_transfer(
+        ctx: Context<ProposeDeviceTransfer>,
+        device_id: u32,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        let device = &mut ctx.accounts.device;
+
+        require!(
+            device.device_state == DeviceState::Available,
+            ErrorCode::DeviceNotAvailable
+        );
+        require_keys_neq!(
+            new_owner,
+            device.authority,
+            ErrorCode::InvalidTransferTarget
+        );
+
+        device.pending_owner = Some(new_owner);
+
+        // Nominate the program's transfer signer on the oracle feed, if given. It
+        // hands the feed to whichever wallet the new owner uses when accepting.
+        if let Some((device_feed, oracle_program)) = oracle_accounts(
+            ctx.accounts.device_feed.as_ref(),
+            ctx.accounts.oracle_program.as_ref(),
+        )? {
+            require_keys_eq!(
+                device_feed.authority,
+                ctx.accounts.authority.key(),
+                ErrorCode::FeedAuthorityMismatch
+            );
+
+            let cpi_context = CpiContext::new(
+                oracle_program.to_account_info(),
+                oracle::cpi::accounts::ProposeFeedAuthority {
+                    feed: device_feed.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            );
+            oracle::cpi::propose_feed_authority(
+                cpi_context,
+                device_id,
+                ctx.accounts.feed_transfer_authority.key(),
+            )?;
+        }
+
+        emit!(DeviceTransferProposed {
+            device_id,
+            from: device.authority,
+            to: new_owner,
+        });
+
+        Ok(())
+    }
+
+    // Withdraw a pending device transfer
+    pub fn cancel_device_transfer(ctx: Context<CancelDeviceTransfer>, device_id: u32) -> Result<()> {
+        let device = &mut ctx.accounts.device;
+
+        require!(device.pending_owner.is_some(), ErrorCode::NoPendingTransfer);
+        device.pending_owner = None;
+
+        if let Some((device_feed, oracle_program)) = oracle_accounts(
+            ctx.accounts.device_feed.as_ref(),
+            ctx.accounts.oracle_program.as_ref(),
+        )? {
+            let cpi_context = CpiContext::new(
+                oracle_program.to_account_info(),
+                oracle::cpi::accounts::ProposeFeedAuthority {
+                    feed: device_feed.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            );
+            oracle::cpi::propose_feed_authority(cpi_context, device_id, Pubkey::default())?;
+        }
+
+        emit!(DeviceTransferCancelled {
+            device_id,
+            authority: device.authority,
+        });
+
+        Ok(())
+    }
+
+    // Accept a proposed device transfer, moving the device and its counters to the caller
+    pub fn accept_device_transfer(ctx: Context<AcceptDeviceTransfer>, device_id: u32) -> Result<()> {
+        let device = &ctx.accounts.device;
+        let new_owner = ctx.accounts.new_owner_provider.provider_key;
+
+        require!(
+            device.pending_owner == Some(new_owner),
+            ErrorCode::NoPendingTransfer
+        );
+        require!(
+            device.device_state == DeviceState::Available,
+            ErrorCode::DeviceNotAvailable
+        );
+
+        let new_device = &mut ctx.accounts.new_device;
+        new_device.authority = new_owner;
+        new_device.provider = ctx.accounts.new_owner_provider.key();
+        new_device.device_id = device_id;
+        new_device.device_state = DeviceState::Available;
+        new_device.location = device.location.clone();
+        new_device.specs = device.specs.clone();
+        new_device.current_campaign = None;
+        new_device.pending_owner = None;
+        new_device.last_uptime_check = device.last_uptime_check;
+        new_device.bump = ctx.bumps.new_device;
+
+        let device_index = &mut ctx.accounts.device_index;
+        device_index.authority = new_owner;
+        device_index.ad_provider = new_device.provider;
+        device_index.device = new_device.key();
+
+        let previous_metadata = &mut ctx.accounts.previous_owner_metadata;
+        previous_metadata.device_count = previous_metadata
+            .device_count
+            .checked_sub(1)
+            .ok_or(ErrorCode::CalculationError)?;
+        previous_metadata.available_devices = previous_metadata
+            .available_devices
+            .checked_sub(1)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        let new_metadata = &mut ctx.accounts.new_owner_metadata;
+        new_metadata.device_count += 1;
+        new_metadata.available_devices += 1;
+
+        // The order record would otherwise linger under the previous owner
+        let device_order = ctx.accounts.device_order.to_account_info();
+        if device_order.owner == &crate::ID {
+            DeviceOrder::try_deserialize(&mut &device_order.try_borrow_data()?[..])?;
+
+            let rent = device_order.lamports();
+            **device_order.try_borrow_mut_lamports()? -= rent;
+            **ctx.accounts.previous_owner.to_account_info().try_borrow_mut_lamports()? += rent;
+            device_order.assign(&system_program::ID);
+            device_order.realloc(0, false)?;
+        }
+
+        // The transfer signer takes the feed nominated at proposal time and passes
+        // it straight on to the new owner's current wallet
+        let device_feed = ctx.accounts.device_feed.to_account_info();
+        let feed_nominated = device_feed.owner == &oracle::ID
+            && DeviceFeed::try_deserialize(&mut &device_feed.try_borrow_data()?[..])?
+                .pending_authority
+                == ctx.accounts.feed_transfer_authority.key();
+        let feed_transferred = if feed_nominated {
+            let oracle_program = ctx
+                .accounts
+                .oracle_program
+                .as_ref()
+                .ok_or(ErrorCode::OracleAccountsRequired)?;
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"feed_transfer".as_ref(),
+                &[ctx.bumps.feed_transfer_authority],
+            ]];
+
+            let cpi_context = CpiContext::new_with_signer(
+                oracle_program.to_account_info(),
+                oracle::cpi::accounts::AcceptFeedAuthority {
+                    feed: device_feed.to_account_info(),
+                    new_authority: ctx.accounts.feed_transfer_authority.to_account_info(),
+                },
+                signer_seeds,
+            );
+            oracle::cpi::accept_feed_authority(cpi_context, device_id)?;
+
+            let cpi_context = CpiContext::new_with_signer(
+                oracle_program.to_account_info(),
+                oracle::cpi::accounts::SetFeedAuthority {
+                    feed: device_feed.to_account_info(),
+                    authority: ctx.accounts.feed_transfer_authority.to_account_info(),
+                },
+                signer_seeds,
+            );
+            oracle::cpi::set_feed_authority(cpi_context, device_id, ctx.accounts.new_owner.key())?;
+            true
+        } else {
+            false
+        };
+
+        emit!(DeviceTransferred {
+            device_id,
+            from: device.authority,
+            to: new_owner,
+            feed_transferred,
+        });
+
+        Ok(())
+    }
+
     // Create campaign with fee structure
+    #[allow(clippy::too_many_arguments)]
     pub fn create_campaign(
         ctx: Context<CreateCampaign>,
         campaign_id: u32,
@@ -112,23 +619,80 @@ pub mod soulboard_core {
         running_days: u32,
         hours_per_day: u32,
         base_fee_per_hour: u64, // in lamports
+        start_ts: i64,
+        end_ts: i64,
     ) -> Result<()> {
+        validate_text(
+            &campaign_name,
+            MAX_CAMPAIGN_NAME_LENGTH,
+            ErrorCode::CampaignNameEmpty,
+            ErrorCode::CampaignNameTooLong,
+        )?;
+        require!(
+            campaign_description.len() <= MAX_CAMPAIGN_DESCRIPTION_LENGTH,
+            ErrorCode::CampaignDescriptionTooLong
+        );
+
         let campaign = &mut ctx.accounts.campaign;
+
+        // A campaign can't be scheduled to have started before it existed
+        require!(
+            start_ts >= Clock::get()?.unix_timestamp && end_ts > start_ts,
+            ErrorCode::InvalidCampaignSchedule
+        );
+
+        // Every running day's hours have to fit between start and end
+        require!(hours_per_day <= HOURS_PER_DAY, ErrorCode::ScheduleExceedsWindow);
+        let scheduled_span = match running_days.checked_sub(1) {
+            Some(full_days) => full_days as i64 * SECONDS_PER_DAY + hours_per_day as i64 * SECONDS_PER_HOUR,
+            None => 0,
+        };
+        require!(
+            scheduled_span <= end_ts - start_ts,
+            ErrorCode::ScheduleExceedsWindow
+        );
+
+        // A token campaign needs its escrow token account created alongside it
+        let budget_mint = ctx.accounts.budget_mint.as_ref().map(|mint| mint.key());
+        if budget_mint.is_some() {
+            require!(
+                ctx.accounts.campaign_token_account.is_some(),
+                ErrorCode::TokenAccountsRequired
+            );
+        }
+
         campaign.authority = ctx.accounts.authority.key();
         campaign.campaign_id = campaign_id;
         campaign.campaign_name = campaign_name;
         campaign.campaign_description = campaign_description;
         campaign.campaign_budget = 0;
+        campaign.budget_mint = budget_mint;
         campaign.campaign_status = CampaignStatus::Active;
         campaign.campaign_providers = Vec::new();
         campaign.campaign_locations = Vec::new();
         campaign.running_days = running_days;
         campaign.hours_per_day = hours_per_day;
+        campaign.start_ts = start_ts;
+        campaign.end_ts = end_ts;
+        campaign.ended_at = 0;
+        campaign.paused_at = 0;
+        campaign.total_paused_seconds = 0;
         campaign.base_fee_per_hour = base_fee_per_hour;
         campaign.platform_fee = 0;
+        campaign.platform_fee_bps_override = None;
         campaign.total_distributed = 0;
+        campaign.fees_calculated = false;
+        campaign.settled_at = 0;
+        campaign.total_withdrawn = 0;
+        campaign.platform_fee_collected = 0;
+        campaign.budget_refunded = 0;
+        campaign.earnings_forfeited = 0;
         campaign.campaign_performance = Vec::new();
 
+        let campaign_vault = &mut ctx.accounts.campaign_vault;
+        campaign_vault.campaign = campaign.key();
+        campaign_vault.bump = ctx.bumps.campaign_vault;
+
         emit!(CampaignCreated {
             authority: ctx.accounts.authority.key(),
         });
@@ -139,23 +703,45 @@ pub mod soulboard_core {
     pub fn add_budget(ctx: Context<AddBudget>, _campaign_id: u32, amount: u64) -> Result<()> {
         let campaign = &mut ctx.accounts.campaign;
 
-        let cpi_context = CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            system_program::Transfer {
-                from: ctx.accounts.authority.to_account_info(),
-                to: campaign.to_account_info(),
-            },
-        );
-        system_program::transfer(cpi_context, amount)?;
+        require!(!campaign.fees_calculated, ErrorCode::FeesAlreadyCalculated);
+
+        if campaign.budget_mint.is_some() {
+            // SPL campaign: escrow tokens in the campaign-owned token account
+            let (Some(from), Some(to), Some(token_program)) = (
+                ctx.accounts.authority_token_account.as_ref(),
+                ctx.accounts.campaign_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(ErrorCode::TokenAccountsRequired);
+            };
+
+            let cpi_context = CpiContext::new(
+                token_program.to_account_info(),
+                token::Transfer {
+                    from: from.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            );
+            token::transfer(cpi_context, amount)?;
+        } else {
+            let cpi_context = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: ctx.accounts.campaign_vault.to_account_info(),
+                },
+            );
+            system_program::transfer(cpi_context, amount)?;
+        }
 
         campaign.campaign_budget += amount;
         
-        // Calculate platform fee (2% of budget)
-        campaign.platform_fee = campaign.campaign_budget
-            .checked_mul(2)
-            .ok_or(ErrorCode::CalculationError)?
-            .checked_div(100)
-            .ok_or(ErrorCode::CalculationError)?;
+        // Calculate platform fee on the whole budget
+        let fee_bps = campaign
+            .platform_fee_bps_override
+            .unwrap_or(ctx.accounts.platform_config.platform_fee_bps);
+        campaign.platform_fee = calculate_platform_fee(campaign.campaign_budget, fee_bps)?;
 
         emit!(BudgetAdded {
             authority: ctx.accounts.authority.key(),
@@ -171,35 +757,61 @@ pub mod soulboard_core {
         device_id: u32,
     ) -> Result<()> {
         let campaign = &mut ctx.accounts.campaign;
+        let device = &mut ctx.accounts.device;
         let ad_provider = &mut ctx.accounts.ad_provider;
         let metadata = &mut ctx.accounts.provider_metadata;
 
-        // Find the device and change its state to booked
-        let device = ad_provider
-            .devices
-            .iter_mut()
-            .find(|d| d.device_id == device_id)
-            .ok_or(ErrorCode::DeviceNotFound)?;
+        // Only a running campaign can take new devices
+        require!(
+            campaign.campaign_status == CampaignStatus::Active
+                || campaign.campaign_status == CampaignStatus::Paused,
+            ErrorCode::CampaignNotActive
+        );
+        require!(!campaign.fees_calculated, ErrorCode::FeesAlreadyCalculated);
 
+        // Change the device's state to booked
         require!(
             device.device_state == DeviceState::Available,
             ErrorCode::DeviceNotAvailable
         );
 
-        device.device_state = DeviceState::Booked;
-
-        campaign.campaign_providers.push(ad_provider.authority);
+        // A paused campaign books the device as paused, so resuming picks it up
+        device.device_state = if campaign.campaign_status == CampaignStatus::Paused {
+            DeviceState::Paused
+        } else {
+            DeviceState::Booked
+        };
+        device.current_campaign = Some(campaign.key());
+
+        // Views the device collected before this booking aren't the campaign's
+        let device_feed = ctx.accounts.device_feed.to_account_info();
+        let (baseline_views, baseline_taps) = if device_feed.owner == &oracle::ID {
+            let feed = DeviceFeed::try_deserialize(&mut &device_feed.try_borrow_data()?[..])?;
+            (feed.total_views, feed.total_taps)
+        } else {
+            (0, 0)
+        };
+
+        campaign.campaign_providers.push(ad_provider.provider_key);
         campaign.campaign_locations.push(location);
 
         // Initialize performance tracking for this provider
         campaign.campaign_performance.push(ProviderPerformance {
-            provider: ad_provider.authority,
+            provider: ad_provider.provider_key,
             device_id,
+            device: device.key(),
             total_views: 0,
             total_taps: 0,
             calculated_earnings: 0,
             base_fee_earned: 0,
             performance_fee_earned: 0,
+            withdrawn_amount: 0,
+            claimed: false,
+            booked_at: Clock::get()?.unix_timestamp,
+            attributed_until: 0,
+            baseline_views,
+            baseline_taps,
+            released: false,
         });
 
         // Update provider metadata
@@ -224,34 +836,51 @@ pub mod soulboard_core {
         ctx: Context<RemoveLocation>,
         campaign_id: u32,
         location: Pubkey,
-        device_id: u32,
+        _device_id: u32,
     ) -> Result<()> {
         let campaign = &mut ctx.accounts.campaign;
-        let ad_provider = &mut ctx.accounts.ad_provider;
+        let device = &mut ctx.accounts.device;
+        let ad_provider = &ctx.accounts.ad_provider;
         let metadata = &mut ctx.accounts.provider_metadata;
 
-        // Find the device and change its state to available
-        let device = ad_provider
-            .devices
-            .iter_mut()
-            .find(|d| d.device_id == device_id)
-            .ok_or(ErrorCode::DeviceNotFound)?;
+        // Once a campaign has ended its devices are released by settlement,
+        // which needs every booking's performance record
+        require!(
+            campaign.campaign_status == CampaignStatus::Active
+                || campaign.campaign_status == CampaignStatus::Paused,
+            ErrorCode::CampaignNotActive
+        );
+        require!(!campaign.fees_calculated, ErrorCode::FeesAlreadyCalculated);
 
+        // Change the device's state back to available
         require!(
-            device.device_state == DeviceState::Booked,
+            (device.device_state == DeviceState::Booked
+                || device.device_state == DeviceState::Paused)
+                && device.current_campaign == Some(campaign.key()),
             ErrorCode::DeviceNotBooked
         );
 
         device.device_state = DeviceState::Available;
+        device.current_campaign = None;
 
-        // Remove the provider and location from the campaign
-        campaign
+        // Remove this device's provider and location entries from the campaign
+        if let Some(index) = campaign
             .campaign_providers
-            .retain(|&p| p != ad_provider.authority);
-        campaign.campaign_locations.retain(|&l| l != location);
+            .iter()
+            .position(|&p| p == ad_provider.provider_key)
+        {
+            campaign.campaign_providers.remove(index);
+        }
+        if let Some(index) = campaign
+            .campaign_locations
+            .iter()
+            .position(|&l| l == location)
+        {
+            campaign.campaign_locations.remove(index);
+        }
 
         // Remove performance tracking
-        campaign.campaign_performance.retain(|p| p.provider != ad_provider.authority);
+        campaign.campaign_performance.retain(|p| p.device != device.key());
 
         // Update metadata
         metadata.available_devices += 1;
@@ -269,7 +898,10 @@ pub mod soulboard_core {
         Ok(())
     }
 
-    // Update campaign performance by fetching data from oracle
+    // Update campaign performance by fetching data from oracle. Anyone can crank
+    // this; with feed history it should run at least once per FEED_HISTORY_HOURS,
+    // since hours that roll out of the oracle's ring first are lost and only
+    // reported in the event.
     pub fn update_campaign_performance(
         ctx: Context<UpdateCampaignPerformance>,
         campaign_id: u32,
@@ -277,6 +909,19 @@ pub mod soulboard_core {
     ) -> Result<()> {
         let campaign = &mut ctx.accounts.campaign;
         let device_feed = &ctx.accounts.device_feed;
+        let now = Clock::get()?.unix_timestamp;
+
+        // Settlement already paid out on the recorded totals
+        require!(!campaign.fees_calculated, ErrorCode::FeesAlreadyCalculated);
+
+        // Only the booking window counts: from when the device joined (or the
+        // campaign started, if later) until the campaign stopped or is due to end
+        let window_end = if campaign.ended_at > 0 {
+            campaign.ended_at
+        } else {
+            campaign.end_ts
+        };
+        let campaign_start = campaign.start_ts;
 
         // Find the performance record for this device
         let performance = campaign
@@ -285,21 +930,99 @@ pub mod soulboard_core {
             .find(|p| p.device_id == device_id)
             .ok_or(ErrorCode::DeviceNotFound)?;
 
-        // Update performance metrics from oracle
-        performance.total_views = device_feed.total_views;
-        performance.total_taps = device_feed.total_taps;
+        let mut hours_expired: u32 = 0;
+        if device_feed.has_history {
+            let history = ctx
+                .accounts
+                .feed_history
+                .as_ref()
+                .ok_or(ErrorCode::FeedHistoryRequired)?;
+
+            // The first crank on history takes over from the lifetime totals:
+            // views from before the history began only show up in the totals
+            // the oracle kept when it started
+            if performance.attributed_until < history.started_at {
+                let booked_from = performance.booked_at.max(campaign_start);
+                if booked_from < history.started_at {
+                    count_lifetime_totals(
+                        performance,
+                        history.start_views,
+                        history.start_taps,
+                        history.started_at,
+                        booked_from,
+                        window_end,
+                    )?;
+                } else {
+                    performance.total_views = 0;
+                    performance.total_taps = 0;
+                }
+                performance.attributed_until =
+                    booked_from.max(history.started_at).min(window_end);
+            }
+
+            // Count finished hours only. Hours cut by the window edges (or by
+            // the history starting mid-hour) count pro rata.
+            let from = performance.attributed_until;
+            let current_hour_start = now - now.rem_euclid(SECONDS_PER_HOUR);
+            let until = window_end.min(current_hour_start);
+
+            if until > from {
+                let from_hour = from.div_euclid(SECONDS_PER_HOUR);
+                let until_hour = (until - 1).div_euclid(SECONDS_PER_HOUR) + 1;
+                for (slot, bucket) in history.buckets.iter().enumerate() {
+                    // A slot holding a later hour overwrote the window's hours
+                    // that mapped onto it
+                    hours_expired = hours_expired.saturating_add(hours_in_slot(
+                        slot as i64,
+                        from_hour,
+                        until_hour.min(bucket.hour),
+                    ) as u32);
+
+                    let bucket_start = (bucket.hour * SECONDS_PER_HOUR).max(history.started_at);
+                    let bucket_end = (bucket.hour + 1) * SECONDS_PER_HOUR;
+                    let overlap = until.min(bucket_end) - from.max(bucket_start);
+                    if overlap <= 0 {
+                        continue;
+                    }
+                    let span = bucket_end - bucket_start;
+                    performance.total_views = performance
+                        .total_views
+                        .checked_add(pro_rata(bucket.views, overlap, span)?)
+                        .ok_or(ErrorCode::CalculationError)?;
+                    performance.total_taps = performance
+                        .total_taps
+                        .checked_add(pro_rata(bucket.taps, overlap, span)?)
+                        .ok_or(ErrorCode::CalculationError)?;
+                }
+                performance.attributed_until = until;
+            }
+        } else {
+            // Feeds without history only expose lifetime totals, so count
+            // what was added since the booking snapshot
+            count_lifetime_totals(
+                performance,
+                device_feed.total_views,
+                device_feed.total_taps,
+                device_feed.last_update_ts,
+                campaign_start,
+                window_end,
+            )?;
+        }
 
         emit!(PerformanceUpdated {
             campaign_id,
             device_id,
             total_views: performance.total_views,
             total_taps: performance.total_taps,
+            hours_expired,
         });
 
         Ok(())
     }
 
-    // Calculate and distribute fees based on performance
+    // Calculate and distribute fees based on performance. Anyone may settle a
+    // completed campaign; sync_campaign_device then frees each device and
+    // credits its provider, one entry per call.
     pub fn calculate_and_distribute_fees(
         ctx: Context<CalculateFees>,
         campaign_id: u32,
@@ -310,81 +1033,12 @@ pub mod soulboard_core {
             campaign.campaign_status == CampaignStatus::Completed,
             ErrorCode::CampaignNotCompleted
         );
+        require!(!campaign.fees_calculated, ErrorCode::FeesAlreadyCalculated);
 
-        // Extract all values we need before mutable borrow to avoid borrow checker issues
-        let total_campaign_hours = campaign.running_days
-            .checked_mul(campaign.hours_per_day)
-            .ok_or(ErrorCode::CalculationError)?;
-
-        let num_asps = campaign.campaign_providers.len() as u64;
-        let total_hours_u64 = total_campaign_hours as u64;
-        let base_fee_per_hour = campaign.base_fee_per_hour;
-        let campaign_budget = campaign.campaign_budget;
-        let platform_fee = campaign.platform_fee;
-        
-        // Calculate total base fees for all ASPs
-        let total_base_fees = total_hours_u64
-            .checked_mul(base_fee_per_hour)
-            .ok_or(ErrorCode::CalculationError)?
-            .checked_mul(num_asps)
-            .ok_or(ErrorCode::CalculationError)?;
-
-        // Calculate distribution fee (remaining budget after base fees and platform fee)
-        let available_for_distribution = campaign_budget
-            .checked_sub(total_base_fees)
-            .ok_or(ErrorCode::InsufficientBudget)?
-            .checked_sub(platform_fee)
-            .ok_or(ErrorCode::InsufficientBudget)?;
-
-        // Calculate total views across all devices
-        let total_views: u64 = campaign.campaign_performance
-            .iter()
-            .map(|p| p.total_views)
-            .sum();
-
-        require!(total_views > 0, ErrorCode::NoViews);
-
-        // Calculate earnings for each ASP
-        for performance in campaign.campaign_performance.iter_mut() {
-            // Base fee calculation (individual ASP gets paid for all campaign hours)
-            let base_fee = total_hours_u64
-                .checked_mul(base_fee_per_hour)
-                .ok_or(ErrorCode::CalculationError)?;
-
-            // Performance-based distribution
-            let performance_share = available_for_distribution
-                .checked_mul(performance.total_views)
-                .ok_or(ErrorCode::CalculationError)?
-                .checked_div(total_views)
-                .ok_or(ErrorCode::CalculationError)?;
-
-            // Total before platform fee
-            let total_before_platform_fee = base_fee
-                .checked_add(performance_share)
-                .ok_or(ErrorCode::CalculationError)?;
-
-            // Platform fee (2% of total earnings)
-            let provider_platform_fee = total_before_platform_fee
-                .checked_mul(2)
-                .ok_or(ErrorCode::CalculationError)?
-                .checked_div(100)
-                .ok_or(ErrorCode::CalculationError)?;
-
-            // Final earnings after platform fee
-            let final_earnings = total_before_platform_fee
-                .checked_sub(provider_platform_fee)
-                .ok_or(ErrorCode::CalculationError)?;
-
-            performance.base_fee_earned = base_fee;
-            performance.performance_fee_earned = performance_share;
-            performance.calculated_earnings = final_earnings;
-        }
-
-        // Calculate total distributed after all calculations are done
-        campaign.total_distributed = campaign.campaign_performance
-            .iter()
-            .map(|p| p.calculated_earnings)
-            .sum();
+        let fee_bps = campaign
+            .platform_fee_bps_override
+            .unwrap_or(ctx.accounts.platform_config.platform_fee_bps);
+        settle_campaign(campaign, fee_bps)?;
 
         emit!(FeesCalculated {
             campaign_id,
@@ -402,31 +1056,66 @@ pub mod soulboard_core {
         let campaign = &mut ctx.accounts.campaign;
         let ad_provider = &mut ctx.accounts.ad_provider;
 
-        // Find the provider's performance in this campaign
-        let performance = campaign
+        // Collect the unclaimed earnings across the provider's devices in this campaign
+        let mut found = false;
+        let mut total_earnings: u64 = 0;
+        let mut earnings: u64 = 0;
+        for performance in campaign
             .campaign_performance
-            .iter()
-            .find(|p| p.provider == ad_provider.authority)
-            .ok_or(ErrorCode::ProviderNotInCampaign)?;
-
-        require!(
-            performance.calculated_earnings > 0,
-            ErrorCode::NoEarningsToWithdraw
-        );
+            .iter_mut()
+            .filter(|p| p.provider == ad_provider.provider_key)
+        {
+            found = true;
+            total_earnings = total_earnings
+                .checked_add(performance.calculated_earnings)
+                .ok_or(ErrorCode::CalculationError)?;
+            // Already withdrawn, or forfeited after the claim window
+            if performance.claimed {
+                continue;
+            }
+            // Earnings become pending on the provider when the entry is released
+            require!(performance.released, ErrorCode::BookingNotReleased);
+
+            let unclaimed = performance
+                .calculated_earnings
+                .checked_sub(performance.withdrawn_amount)
+                .ok_or(ErrorCode::CalculationError)?;
+            earnings = earnings
+                .checked_add(unclaimed)
+                .ok_or(ErrorCode::CalculationError)?;
 
-        let earnings = performance.calculated_earnings;
+            performance.withdrawn_amount = performance.calculated_earnings;
+            performance.claimed = true;
+        }
 
-        // Transfer earnings from campaign to provider
-        **campaign.to_account_info().try_borrow_mut_lamports()? -= earnings;
-        **ad_provider.to_account_info().try_borrow_mut_lamports()? += earnings;
+        require!(found, ErrorCode::ProviderNotInCampaign);
+        require!(total_earnings > 0, ErrorCode::NoEarningsToWithdraw);
+        require!(earnings > 0, ErrorCode::EarningsAlreadyClaimed);
+
+        // Pay out of the campaign vault
+        pay_from_vault(
+            campaign,
+            &ctx.accounts.campaign_vault,
+            &ctx.accounts.authority.to_account_info(),
+            ctx.accounts.campaign_token_account.as_ref(),
+            ctx.accounts.provider_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            earnings,
+        )?;
+
+        campaign.total_withdrawn = campaign.total_withdrawn
+            .checked_add(earnings)
+            .ok_or(ErrorCode::CalculationError)?;
 
         // Update provider's total earnings
         ad_provider.total_earnings = ad_provider.total_earnings
             .checked_add(earnings)
             .ok_or(ErrorCode::CalculationError)?;
+        // Saturating so campaigns settled before pending payments were tracked can still pay out
+        ad_provider.pending_payments = ad_provider.pending_payments.saturating_sub(earnings);
 
         emit!(EarningsWithdrawn {
-            provider: ad_provider.authority,
+            provider: ad_provider.provider_key,
             campaign_id,
             amount: earnings,
         });
@@ -434,6 +1123,232 @@ pub mod soulboard_core {
         Ok(())
     }
 
+    // Move the platform fee accumulated by a settled campaign to the treasury
+    pub fn collect_platform_fee(
+        ctx: Context<CollectPlatformFee>,
+        campaign_id: u32,
+    ) -> Result<()> {
+        let campaign = &mut ctx.accounts.campaign;
+
+        require!(campaign.fees_calculated, ErrorCode::FeesNotCalculated);
+
+        let amount = campaign
+            .platform_fee
+            .checked_sub(campaign.platform_fee_collected)
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(amount > 0, ErrorCode::NoPlatformFeeToCollect);
+
+        pay_from_vault(
+            campaign,
+            &ctx.accounts.campaign_vault,
+            &ctx.accounts.treasury.to_account_info(),
+            ctx.accounts.campaign_token_account.as_ref(),
+            ctx.accounts.treasury_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            amount,
+        )?;
+
+        campaign.platform_fee_collected = campaign.platform_fee;
+
+        emit!(PlatformFeeCollected {
+            campaign: campaign.key(),
+            campaign_id,
+            treasury: ctx.accounts.treasury.key(),
+            budget_mint: campaign.budget_mint,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    // Return budget that was never allocated to providers or the platform to the
+    // advertiser. Once the claim window has passed, earnings providers never
+    // withdrew are forfeited back to the advertiser as well; remaining accounts
+    // then carry the ad_provider of each campaign_performance entry, in order
+    // (any account will do for entries with nothing left to claim).
+    pub fn refund_remaining_budget<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RefundRemainingBudget<'info>>,
+        campaign_id: u32,
+    ) -> Result<()> {
+        let campaign = &mut ctx.accounts.campaign;
+
+        require!(campaign.fees_calculated, ErrorCode::FeesNotCalculated);
+
+        let undistributed = campaign
+            .campaign_budget
+            .checked_sub(campaign.total_distributed)
+            .ok_or(ErrorCode::CalculationError)?
+            .checked_sub(campaign.platform_fee)
+            .ok_or(ErrorCode::CalculationError)?
+            .checked_sub(campaign.budget_refunded)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        let claim_window_end = campaign
+            .settled_at
+            .checked_add(CLAIM_WINDOW_SECONDS)
+            .ok_or(ErrorCode::CalculationError)?;
+        let mut forfeited: u64 = 0;
+        if Clock::get()?.unix_timestamp >= claim_window_end {
+            require!(
+                ctx.remaining_accounts.len() == campaign.campaign_performance.len(),
+                ErrorCode::InvalidRemainingAccounts
+            );
+
+            for (performance, provider_info) in campaign
+                .campaign_performance
+                .iter_mut()
+                .zip(ctx.remaining_accounts.iter())
+            {
+                if performance.claimed {
+                    continue;
+                }
+
+                let unclaimed = performance
+                    .calculated_earnings
+                    .checked_sub(performance.withdrawn_amount)
+                    .ok_or(ErrorCode::CalculationError)?;
+                forfeited = forfeited
+                    .checked_add(unclaimed)
+                    .ok_or(ErrorCode::CalculationError)?;
+                performance.claimed = true;
+
+                // Only released entries were credited to the provider. Otherwise
+                // don't load it: it may have deregistered since and its slot can
+                // hold any account
+                if unclaimed == 0 || !performance.released {
+                    continue;
+                }
+
+                // Written back per entry, as a provider can appear more than once
+                let mut ad_provider = Account::<AdProvider>::try_from(provider_info)?;
+                require_keys_eq!(
+                    ad_provider.provider_key,
+                    performance.provider,
+                    ErrorCode::InvalidRemainingAccounts
+                );
+
+                ad_provider.pending_payments = ad_provider.pending_payments.saturating_sub(unclaimed);
+                ad_provider.exit(&crate::ID)?;
+            }
+        }
+
+        let amount = undistributed
+            .checked_add(forfeited)
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(amount > 0, ErrorCode::NoBudgetToRefund);
+
+        pay_from_vault(
+            campaign,
+            &ctx.accounts.campaign_vault,
+            &ctx.accounts.authority.to_account_info(),
+            ctx.accounts.campaign_token_account.as_ref(),
+            ctx.accounts.authority_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            amount,
+        )?;
+
+        campaign.budget_refunded = campaign
+            .budget_refunded
+            .checked_add(undistributed)
+            .ok_or(ErrorCode::CalculationError)?;
+        campaign.earnings_forfeited = campaign
+            .earnings_forfeited
+            .checked_add(forfeited)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        emit!(BudgetRefunded {
+            campaign_id,
+            authority: ctx.accounts.authority.key(),
+            amount,
+            earnings_forfeited: forfeited,
+        });
+
+        Ok(())
+    }
+
+    // Close a settled campaign, returning rent to the advertiser
+    pub fn close_campaign(
+        ctx: Context<CloseCampaign>,
+        campaign_id: u32,
+    ) -> Result<()> {
+        let campaign = &ctx.accounts.campaign;
+
+        require!(
+            campaign.campaign_status == CampaignStatus::Completed
+                || campaign.campaign_status == CampaignStatus::Cancelled,
+            ErrorCode::CampaignNotCompleted
+        );
+        require!(campaign.fees_calculated, ErrorCode::FeesNotCalculated);
+        // Devices still pointing at the campaign couldn't be freed once it's gone
+        require!(
+            campaign.campaign_performance.iter().all(|p| p.released),
+            ErrorCode::BookingNotReleased
+        );
+        let settled_earnings = campaign
+            .total_withdrawn
+            .checked_add(campaign.earnings_forfeited)
+            .ok_or(ErrorCode::CalculationError)?;
+        require!(
+            settled_earnings >= campaign.total_distributed,
+            ErrorCode::EarningsNotWithdrawn
+        );
+        require!(
+            campaign.platform_fee_collected >= campaign.platform_fee,
+            ErrorCode::PlatformFeeNotCollected
+        );
+
+        // Token escrow has to be emptied before it can be closed; SOL left in
+        // the vault is returned when the vault account itself is closed
+        if campaign.budget_mint.is_some() {
+            let (Some(campaign_token_account), Some(authority_token_account), Some(token_program)) = (
+                ctx.accounts.campaign_token_account.as_ref(),
+                ctx.accounts.authority_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+            ) else {
+                return err!(ErrorCode::TokenAccountsRequired);
+            };
+
+            let campaign_vault = &ctx.accounts.campaign_vault;
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"campaign_vault".as_ref(),
+                campaign_vault.campaign.as_ref(),
+                &[campaign_vault.bump],
+            ]];
+
+            if campaign_token_account.amount > 0 {
+                token::transfer(
+                    CpiContext::new_with_signer(
+                        token_program.to_account_info(),
+                        token::Transfer {
+                            from: campaign_token_account.to_account_info(),
+                            to: authority_token_account.to_account_info(),
+                            authority: campaign_vault.to_account_info(),
+                        },
+                        signer_seeds,
+                    ),
+                    campaign_token_account.amount,
+                )?;
+            }
+
+            token::close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                token::CloseAccount {
+                    account: campaign_token_account.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: campaign_vault.to_account_info(),
+                },
+                signer_seeds,
+            ))?;
+        }
+
+        emit!(CampaignClosed {
+            campaign_id,
+            authority: ctx.accounts.authority.key(),
+        });
+
+        Ok(())
+    }
+
     // Update provider information
     pub fn update_provider(
         ctx: Context<UpdateProvider>,
@@ -445,6 +1360,22 @@ pub mod soulboard_core {
         let ad_provider = &mut ctx.accounts.ad_provider;
         let metadata = &mut ctx.accounts.provider_metadata;
 
+        // Validate everything before applying anything
+        if let Some(name) = name.as_deref() {
+            validate_text(name, MAX_NAME_LENGTH, ErrorCode::NameEmpty, ErrorCode::NameTooLong)?;
+        }
+        if let Some(location) = location.as_deref() {
+            validate_text(
+                location,
+                MAX_LOCATION_LENGTH,
+                ErrorCode::LocationEmpty,
+                ErrorCode::LocationTooLong,
+            )?;
+        }
+        if let Some(email) = contact_email.as_deref() {
+            validate_email(email)?;
+        }
+
         if let Some(name) = name {
             ad_provider.name = name.clone();
             metadata.name = name;
@@ -467,29 +1398,847 @@ pub mod soulboard_core {
         Ok(())
     }
 
-    // Complete a campaign (mark as completed for fee calculation)
+    // Advertiser review of a provider once their campaign has completed and
+    // settled, limited to one per campaign and provider. Only providers the
+    // campaign actually paid can be reviewed, so reviews can't be farmed from
+    // empty campaigns.
+    pub fn submit_review(
+        ctx: Context<SubmitReview>,
+        _campaign_id: u32,
+        provider_key: Pubkey,
+        score: u8,
+    ) -> Result<()> {
+        let campaign = &ctx.accounts.campaign;
+
+        require!(
+            campaign.campaign_status == CampaignStatus::Completed,
+            ErrorCode::CampaignNotCompleted
+        );
+        require!(campaign.fees_calculated, ErrorCode::FeesNotCalculated);
+        require!(
+            campaign.campaign_providers.contains(&provider_key),
+            ErrorCode::ProviderNotInCampaign
+        );
+        require!(
+            campaign
+                .campaign_performance
+                .iter()
+                .any(|p| p.provider == provider_key && p.calculated_earnings > 0),
+            ErrorCode::ProviderNotPaid
+        );
+        require!(score <= MAX_RATING, ErrorCode::InvalidReviewScore);
+
+        let review = &mut ctx.accounts.review;
+        review.campaign = campaign.key();
+        review.provider = provider_key;
+        review.reviewer = ctx.accounts.authority.key();
+        review.score = score;
+        review.created_at = Clock::get()?.unix_timestamp;
+        review.bump = ctx.bumps.review;
+
+        let ad_provider = &mut ctx.accounts.ad_provider;
+        ad_provider.review_count += 1;
+        ad_provider.review_score_total += score as u64;
+        refresh_rating(ad_provider, &mut ctx.accounts.provider_metadata);
+
+        emit!(ReviewSubmitted {
+            campaign: campaign.key(),
+            provider_key,
+            score,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless crank folding a device's uptime into its provider's rating.
+    // When it runs doesn't change the outcome: every finished hour since the last
+    // crank is counted once, as up if the feed's history shows an update during
+    // that hour. Hours that already left the history ring count as down, so
+    // skipping cranks can't hide downtime. The first crank only starts the count.
+    pub fn record_device_uptime(ctx: Context<RecordDeviceUptime>, device_id: u32) -> Result<()> {
+        let device = &mut ctx.accounts.device;
+        let now = Clock::get()?.unix_timestamp;
+        let current_hour = now.div_euclid(SECONDS_PER_HOUR);
+
+        require!(
+            device.device_state != DeviceState::Ordered,
+            ErrorCode::InvalidDeviceState
+        );
+
+        let (hours_checked, hours_online) = if device.last_uptime_check == 0 {
+            (0, 0)
+        } else {
+            let from_hour = device.last_uptime_check.div_euclid(SECONDS_PER_HOUR);
+            require!(current_hour > from_hour, ErrorCode::UptimeCheckTooSoon);
+
+            let buckets = &ctx.accounts.feed_history.buckets;
+            let oldest_kept_hour = current_hour - (FEED_HISTORY_HOURS - 1);
+            let hours_online = (from_hour.max(oldest_kept_hour)..current_hour)
+                .filter(|&hour| {
+                    buckets
+                        .get(hour.rem_euclid(FEED_HISTORY_HOURS) as usize)
+                        .is_some_and(|bucket| bucket.hour == hour)
+                })
+                .count() as u32;
+            ((current_hour - from_hour) as u32, hours_online)
+        };
+        device.last_uptime_check = current_hour * SECONDS_PER_HOUR;
+
+        let ad_provider = &mut ctx.accounts.ad_provider;
+        ad_provider.uptime_checks = ad_provider
+            .uptime_checks
+            .checked_add(hours_checked)
+            .ok_or(ErrorCode::CalculationError)?;
+        ad_provider.uptime_hits = ad_provider
+            .uptime_hits
+            .checked_add(hours_online)
+            .ok_or(ErrorCode::CalculationError)?;
+        refresh_rating(ad_provider, &mut ctx.accounts.provider_metadata);
+
+        emit!(DeviceUptimeChecked {
+            device_id,
+            hours_checked,
+            hours_online,
+            ts: now,
+        });
+
+        Ok(())
+    }
+
+    // Leave the platform. The provider must have no devices left and nothing
+    // left to withdraw.
+    pub fn deregister_provider(ctx: Context<DeregisterProvider>) -> Result<()> {
+        let ad_provider = &ctx.accounts.ad_provider;
+        let metadata = &ctx.accounts.provider_metadata;
+        let registry = &mut ctx.accounts.provider_registry;
+        let registry_page = &mut ctx.accounts.registry_page;
+
+        require!(metadata.device_count == 0, ErrorCode::ProviderHasDevices);
+        require!(
+            ad_provider.pending_payments == 0,
+            ErrorCode::ProviderHasPendingPayments
+        );
+
+        let index = registry_page
+            .providers
+            .iter()
+            .position(|&p| p == ad_provider.provider_key)
+            .ok_or(ErrorCode::ProviderNotInRegistry)?;
+        registry_page.providers.remove(index);
+        registry.total_providers -= 1;
+
+        emit!(ProviderDeregistered {
+            provider_key: ad_provider.provider_key,
+            authority: ad_provider.authority,
+            registry_page: ad_provider.registry_page,
+        });
+
+        Ok(())
+    }
+
+    // Set or clear the backup key that can rotate the provider's authority
+    pub fn set_recovery_authority(
+        ctx: Context<SetRecoveryAuthority>,
+        recovery_authority: Option<Pubkey>,
+    ) -> Result<()> {
+        let ad_provider = &mut ctx.accounts.ad_provider;
+        ad_provider.recovery_authority = recovery_authority;
+
+        emit!(RecoveryAuthoritySet {
+            provider_key: ad_provider.provider_key,
+            recovery_authority,
+        });
+
+        Ok(())
+    }
+
+    // Hand the provider to a new wallet. Earnings, devices and bookings are keyed
+    // by provider_key, so they carry over untouched. Oracle feeds passed in the
+    // remaining accounts are moved to the new wallet as well, which needs the
+    // current wallet's signature: after a recovery rotation the feeds stay on the
+    // lost wallet, and their devices can only be decommissioned without them.
+    // A recovery key is single-use and has to be set again afterwards.
+    pub fn rotate_provider_authority<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RotateProviderAuthority<'info>>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        let ad_provider = &mut ctx.accounts.ad_provider;
+        let metadata = &mut ctx.accounts.provider_metadata;
+        let signer = ctx.accounts.signer.key();
+
+        let by_recovery = signer != ad_provider.authority;
+        require!(
+            !by_recovery || ad_provider.recovery_authority == Some(signer),
+            ErrorCode::NotProviderAuthority
+        );
+        require!(
+            new_authority != Pubkey::default() && new_authority != ad_provider.authority,
+            ErrorCode::InvalidNewAuthority
+        );
+
+        if !ctx.remaining_accounts.is_empty() {
+            require!(!by_recovery, ErrorCode::FeedAuthorityMismatch);
+            let Some(oracle_program) = ctx.accounts.oracle_program.as_ref() else {
+                return err!(ErrorCode::OracleAccountsRequired);
+            };
+
+            for feed_info in ctx.remaining_accounts {
+                let device_feed = Account::<DeviceFeed>::try_from(feed_info)?;
+                let cpi_context = CpiContext::new(
+                    oracle_program.to_account_info(),
+                    oracle::cpi::accounts::SetFeedAuthority {
+                        feed: feed_info.clone(),
+                        authority: ctx.accounts.signer.to_account_info(),
+                    },
+                );
+                oracle::cpi::set_feed_authority(cpi_context, device_feed.channel_id, new_authority)?;
+            }
+        }
+
+        let previous_authority = ad_provider.authority;
+        ad_provider.authority = new_authority;
+        metadata.authority = new_authority;
+        if by_recovery {
+            ad_provider.recovery_authority = None;
+        }
+
+        emit!(ProviderAuthorityRotated {
+            provider_key: ad_provider.provider_key,
+            previous_authority,
+            new_authority,
+            by_recovery,
+            feeds_rotated: ctx.remaining_accounts.len() as u32,
+        });
+
+        Ok(())
+    }
+
+    // Complete a campaign once its end time has passed (mark as completed for fee calculation)
     pub fn complete_campaign(ctx: Context<CompleteCampaign>, campaign_id: u32) -> Result<()> {
         let campaign = &mut ctx.accounts.campaign;
         
         require!(
-            campaign.campaign_status == CampaignStatus::Active,
+            campaign.campaign_status == CampaignStatus::Active
+                || campaign.campaign_status == CampaignStatus::Paused,
             ErrorCode::CampaignNotActive
         );
 
+        let now = Clock::get()?.unix_timestamp;
+        require!(now >= campaign.end_ts, ErrorCode::CampaignNotEnded);
+
+        // A campaign that ends while paused stays unpaid for the rest of its schedule
+        if campaign.campaign_status == CampaignStatus::Paused {
+            let paused_seconds = scheduled_seconds_between(campaign, campaign.paused_at, now);
+            campaign.total_paused_seconds = campaign
+                .total_paused_seconds
+                .checked_add(paused_seconds)
+                .ok_or(ErrorCode::CalculationError)?;
+            campaign.paused_at = 0;
+        }
+
         campaign.campaign_status = CampaignStatus::Completed;
+        campaign.ended_at = campaign.end_ts;
 
         emit!(CampaignCompleted {
+            campaign_id,
+            authority: campaign.authority,
+        });
+
+        Ok(())
+    }
+
+    // Cancel a running campaign early: settle providers for the time served so far
+    // and refund whatever the settlement didn't allocate. The booked devices are
+    // released by sync_campaign_device.
+    pub fn cancel_campaign(ctx: Context<CancelCampaign>, campaign_id: u32) -> Result<()> {
+        let campaign = &mut ctx.accounts.campaign;
+
+        require!(
+            campaign.campaign_status == CampaignStatus::Active
+                || campaign.campaign_status == CampaignStatus::Paused,
+            ErrorCode::CampaignNotActive
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        if campaign.campaign_status == CampaignStatus::Paused {
+            let paused_seconds = scheduled_seconds_between(campaign, campaign.paused_at, now);
+            campaign.total_paused_seconds = campaign
+                .total_paused_seconds
+                .checked_add(paused_seconds)
+                .ok_or(ErrorCode::CalculationError)?;
+            campaign.paused_at = 0;
+        }
+
+        campaign.campaign_status = CampaignStatus::Cancelled;
+        campaign.ended_at = now.min(campaign.end_ts);
+
+        let fee_bps = campaign
+            .platform_fee_bps_override
+            .unwrap_or(ctx.accounts.platform_config.platform_fee_bps);
+        settle_campaign(campaign, fee_bps)?;
+
+        // Providers' earnings and the platform fee stay in the vault until claimed
+        let refund = campaign
+            .campaign_budget
+            .checked_sub(campaign.total_distributed)
+            .ok_or(ErrorCode::CalculationError)?
+            .checked_sub(campaign.platform_fee)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        if refund > 0 {
+            pay_from_vault(
+                campaign,
+                &ctx.accounts.campaign_vault,
+                &ctx.accounts.authority.to_account_info(),
+                ctx.accounts.campaign_token_account.as_ref(),
+                ctx.accounts.authority_token_account.as_ref(),
+                ctx.accounts.token_program.as_ref(),
+                refund,
+            )?;
+            campaign.budget_refunded = refund;
+        }
+
+        emit!(CampaignCancelled {
+            campaign_id,
+            authority: ctx.accounts.authority.key(),
+            total_distributed: campaign.total_distributed,
+            refunded: refund,
+            ts: now,
+        });
+
+        Ok(())
+    }
+
+    // Pause an active campaign; sync_campaign_device pauses its booked devices
+    pub fn pause_campaign(ctx: Context<PauseCampaign>, campaign_id: u32) -> Result<()> {
+        let campaign = &mut ctx.accounts.campaign;
+
+        require!(
+            campaign.campaign_status == CampaignStatus::Active,
+            ErrorCode::CampaignNotActive
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        campaign.campaign_status = CampaignStatus::Paused;
+        campaign.paused_at = now;
+
+        emit!(CampaignPaused {
             campaign_id,
             authority: ctx.accounts.authority.key(),
+            ts: now,
         });
 
         Ok(())
     }
 
-    // Get all providers from registry (view function)
-    pub fn get_all_providers(ctx: Context<QueryProviders>) -> Result<Vec<Pubkey>> {
-        let registry = &ctx.accounts.provider_registry;
-        Ok(registry.providers.clone())
+    // Resume a paused campaign, adding the pause to the time excluded from base fees
+    pub fn resume_campaign(ctx: Context<ResumeCampaign>, campaign_id: u32) -> Result<()> {
+        let campaign = &mut ctx.accounts.campaign;
+
+        require!(
+            campaign.campaign_status == CampaignStatus::Paused,
+            ErrorCode::CampaignNotPaused
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let paused_seconds = scheduled_seconds_between(campaign, campaign.paused_at, now);
+        campaign.total_paused_seconds = campaign
+            .total_paused_seconds
+            .checked_add(paused_seconds)
+            .ok_or(ErrorCode::CalculationError)?;
+        campaign.paused_at = 0;
+        campaign.campaign_status = CampaignStatus::Active;
+
+        emit!(CampaignResumed {
+            campaign_id,
+            authority: ctx.accounts.authority.key(),
+            ts: now,
+        });
+
+        Ok(())
+    }
+
+    // Permissionless crank bringing one booked device in line with its campaign,
+    // so no instruction has to carry every device at once. While the campaign
+    // runs the device follows its paused state; once the campaign is settled the
+    // device is released and its provider credited with the entry's earnings.
+    pub fn sync_campaign_device(
+        ctx: Context<SyncCampaignDevice>,
+        campaign_id: u32,
+        device_id: u32,
+    ) -> Result<()> {
+        let campaign = &mut ctx.accounts.campaign;
+        let campaign_key = campaign.key();
+        let device = &mut ctx.accounts.device;
+        let ad_provider = &mut ctx.accounts.ad_provider;
+        let metadata = &mut ctx.accounts.provider_metadata;
+
+        let status = campaign.campaign_status.clone();
+        let settled = campaign.fees_calculated;
+        let performance = campaign
+            .campaign_performance
+            .iter_mut()
+            .find(|p| p.device_id == device_id)
+            .ok_or(ErrorCode::DeviceNotFound)?;
+        require!(
+            performance.device == device.key() && device.current_campaign == Some(campaign_key),
+            ErrorCode::DeviceNotBooked
+        );
+
+        let mut earnings_credited: u64 = 0;
+        if settled {
+            require!(!performance.released, ErrorCode::InvalidDeviceState);
+
+            // Earnings forfeited after the claim window are no longer owed
+            if !performance.claimed {
+                earnings_credited = performance.calculated_earnings;
+                ad_provider.pending_payments = ad_provider
+                    .pending_payments
+                    .checked_add(earnings_credited)
+                    .ok_or(ErrorCode::CalculationError)?;
+            }
+            // Paid bookings in campaigns that ran to completion count towards the
+            // rating; an advertiser cancelling isn't held against the provider
+            if status == CampaignStatus::Completed && performance.calculated_earnings > 0 {
+                ad_provider.fulfilled_bookings += 1;
+                refresh_rating(ad_provider, metadata);
+            }
+
+            performance.released = true;
+            device.device_state = DeviceState::Available;
+            device.current_campaign = None;
+            metadata.available_devices += 1;
+        } else {
+            let device_state = match status {
+                CampaignStatus::Active => DeviceState::Booked,
+                CampaignStatus::Paused => DeviceState::Paused,
+                _ => return err!(ErrorCode::FeesNotCalculated),
+            };
+            require!(
+                device.device_state != device_state,
+                ErrorCode::InvalidDeviceState
+            );
+            device.device_state = device_state;
+        }
+
+        emit!(CampaignDeviceSynced {
+            campaign_id,
+            device_id,
+            device_state: device.device_state.clone(),
+            earnings_credited,
+        });
+
+        Ok(())
+    }
+
+    // Get one page of providers from the registry (view function)
+    pub fn get_providers_page(
+        ctx: Context<QueryProviders>,
+        _page_index: u32,
+    ) -> Result<Vec<Pubkey>> {
+        let registry_page = &ctx.accounts.registry_page;
+        Ok(registry_page.providers.clone())
+    }
+
+    // Resolve a device id to the AdProvider that owns it (view function)
+    pub fn get_device_provider(
+        ctx: Context<QueryDeviceIndex>,
+        _device_id: u32,
+    ) -> Result<Pubkey> {
+        Ok(ctx.accounts.device_index.ad_provider)
+    }
+}
+
+// Recompute a provider's rating from its reviews, device uptime and booking
+// outcomes. Signals with no data yet count as the default rating.
+fn refresh_rating(ad_provider: &mut AdProvider, metadata: &mut ProviderMetadata) {
+    let percent = |part: u64, whole: u64| {
+        (part * MAX_RATING as u64)
+            .checked_div(whole)
+            .unwrap_or(DEFAULT_PROVIDER_RATING as u64)
+    };
+
+    let review_score = ad_provider
+        .review_score_total
+        .checked_div(ad_provider.review_count as u64)
+        .unwrap_or(DEFAULT_PROVIDER_RATING as u64);
+    let uptime_score = percent(
+        ad_provider.uptime_hits as u64,
+        ad_provider.uptime_checks as u64,
+    );
+    let booking_score = percent(
+        ad_provider.fulfilled_bookings as u64,
+        ad_provider.fulfilled_bookings as u64 + ad_provider.cancelled_bookings as u64,
+    );
+
+    let rating = (review_score * REVIEW_RATING_WEIGHT
+        + uptime_score * UPTIME_RATING_WEIGHT
+        + booking_score * BOOKING_RATING_WEIGHT)
+        / (REVIEW_RATING_WEIGHT + UPTIME_RATING_WEIGHT + BOOKING_RATING_WEIGHT);
+    let rating = rating.min(MAX_RATING as u64) as u8;
+
+    if rating != ad_provider.rating {
+        emit!(RatingUpdated {
+            provider_key: ad_provider.provider_key,
+            previous_rating: ad_provider.rating,
+            rating,
+        });
+    }
+    ad_provider.rating = rating;
+    metadata.rating = rating;
+}
+
+// Split a finished campaign's budget into provider earnings and platform fees.
+// Base fees cover served hours only and are shared pro rata when the budget
+// can't cover them; the remaining pool is shared by views. Whatever isn't
+// allocated (e.g. the pool of a campaign that collected no views) stays
+// refundable to the advertiser.
+fn settle_campaign(campaign: &mut Campaign, fee_bps: u16) -> Result<()> {
+    // Extract all values we need before mutable borrow to avoid borrow checker issues
+    // Only scheduled hours that actually elapsed while running earn base fees
+    let total_hours_u64 = served_campaign_hours(campaign)?;
+
+    let num_asps = campaign.campaign_providers.len() as u64;
+    let base_fee_per_hour = campaign.base_fee_per_hour;
+    let campaign_budget = campaign.campaign_budget;
+    let platform_fee = campaign.platform_fee;
+
+    // Base fee each ASP earned for the served hours
+    let scheduled_base_fee = total_hours_u64
+        .checked_mul(base_fee_per_hour)
+        .ok_or(ErrorCode::CalculationError)?;
+    let total_base_fees = scheduled_base_fee
+        .checked_mul(num_asps)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    let budget_after_fee = campaign_budget
+        .checked_sub(platform_fee)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    // Calculate distribution fee (remaining budget after base fees and platform fee).
+    // If the budget falls short of the base fees, it is split evenly instead.
+    let (base_fee, mut available_for_distribution) = match budget_after_fee.checked_sub(total_base_fees) {
+        Some(pool) => (scheduled_base_fee, pool),
+        None => (budget_after_fee / num_asps, 0),
+    };
+
+    // A cancelled campaign only pays out the share of the pool its served time earned
+    let cancelled = campaign.campaign_status == CampaignStatus::Cancelled;
+    if cancelled {
+        let (served_seconds, scheduled_seconds) = served_schedule_seconds(campaign);
+        available_for_distribution = if scheduled_seconds == 0 {
+            0
+        } else {
+            ((available_for_distribution as u128)
+                .checked_mul(served_seconds as u128)
+                .ok_or(ErrorCode::CalculationError)?
+                / scheduled_seconds as u128) as u64
+        };
+    }
+
+    // Calculate total views across all devices
+    let total_views: u64 = campaign.campaign_performance
+        .iter()
+        .map(|p| p.total_views)
+        .sum();
+
+    // Platform fees withheld from provider earnings, on top of the budget fee
+    let mut provider_platform_fees: u64 = 0;
+    // Budget handed to providers, before their platform fee
+    let mut allocated: u64 = 0;
+
+    // Calculate earnings for each ASP
+    for performance in campaign.campaign_performance.iter_mut() {
+        // Performance-based distribution; with no views the pool is left unallocated
+        let performance_share = if total_views == 0 {
+            0
+        } else {
+            available_for_distribution
+                .checked_mul(performance.total_views)
+                .ok_or(ErrorCode::CalculationError)?
+                .checked_div(total_views)
+                .ok_or(ErrorCode::CalculationError)?
+        };
+
+        // Total before platform fee
+        let total_before_platform_fee = base_fee
+            .checked_add(performance_share)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        // Platform fee on total earnings
+        let provider_platform_fee = calculate_platform_fee(total_before_platform_fee, fee_bps)?;
+
+        // Final earnings after platform fee
+        let final_earnings = total_before_platform_fee
+            .checked_sub(provider_platform_fee)
+            .ok_or(ErrorCode::CalculationError)?;
+
+        performance.base_fee_earned = base_fee;
+        performance.performance_fee_earned = performance_share;
+        performance.calculated_earnings = final_earnings;
+
+        provider_platform_fees = provider_platform_fees
+            .checked_add(provider_platform_fee)
+            .ok_or(ErrorCode::CalculationError)?;
+        allocated = allocated
+            .checked_add(total_before_platform_fee)
+            .ok_or(ErrorCode::CalculationError)?;
+    }
+
+    // The budget fee only covers the share of the budget that was allocated,
+    // so budget returned to the advertiser isn't charged
+    let budget_fee = if budget_after_fee == 0 {
+        platform_fee
+    } else {
+        ((platform_fee as u128)
+            .checked_mul(allocated.min(budget_after_fee) as u128)
+            .ok_or(ErrorCode::CalculationError)?
+            / budget_after_fee as u128) as u64
+    };
+
+    campaign.platform_fee = budget_fee
+        .checked_add(provider_platform_fees)
+        .ok_or(ErrorCode::CalculationError)?;
+    campaign.fees_calculated = true;
+    campaign.settled_at = Clock::get()?.unix_timestamp;
+
+    // Calculate total distributed after all calculations are done
+    campaign.total_distributed = campaign.campaign_performance
+        .iter()
+        .map(|p| p.calculated_earnings)
+        .sum();
+
+    Ok(())
+}
+
+// Seconds between `from` and `to` that fall inside the campaign's schedule
+fn scheduled_seconds_between(campaign: &Campaign, from: i64, to: i64) -> u64 {
+    let from = from.clamp(campaign.start_ts, campaign.end_ts);
+    let to = to.clamp(campaign.start_ts, campaign.end_ts);
+    to.saturating_sub(from) as u64
+}
+
+// Seconds of the schedule the campaign actually ran (excluding pauses), and its full length
+fn served_schedule_seconds(campaign: &Campaign) -> (u64, u64) {
+    let scheduled_seconds = scheduled_seconds_between(campaign, campaign.start_ts, campaign.end_ts);
+    let served_seconds = scheduled_seconds_between(campaign, campaign.start_ts, campaign.ended_at)
+        .saturating_sub(campaign.total_paused_seconds);
+    (served_seconds, scheduled_seconds)
+}
+
+// Campaign hours that were actually served: the share of running_days * hours_per_day
+// matching the share of the schedule that elapsed before the campaign ended, minus pauses
+fn served_campaign_hours(campaign: &Campaign) -> Result<u64> {
+    let total_campaign_hours = (campaign.running_days as u64)
+        .checked_mul(campaign.hours_per_day as u64)
+        .ok_or(ErrorCode::CalculationError)?;
+
+    let (served_seconds, scheduled_seconds) = served_schedule_seconds(campaign);
+    if scheduled_seconds == 0 {
+        return Ok(0);
+    }
+
+    let served_hours = (total_campaign_hours as u128)
+        .checked_mul(served_seconds as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        / scheduled_seconds as u128;
+    Ok(served_hours as u64)
+}
+
+// Add what a feed's lifetime totals, as of `snapshot_ts`, gained since the
+// performance was last counted. Totals straddling either edge of the booking
+// window also hold views from outside it, so only the window's share counts.
+fn count_lifetime_totals(
+    performance: &mut ProviderPerformance,
+    views: u64,
+    taps: u64,
+    snapshot_ts: i64,
+    window_start: i64,
+    window_end: i64,
+) -> Result<()> {
+    let mut counted_from = performance.attributed_until.max(performance.booked_at);
+    if counted_from >= window_end {
+        return Ok(());
+    }
+
+    let mut new_views = views
+        .saturating_sub(performance.baseline_views)
+        .saturating_sub(performance.total_views);
+    let mut new_taps = taps
+        .saturating_sub(performance.baseline_taps)
+        .saturating_sub(performance.total_taps);
+
+    // Views from before the window opened go into the baseline so later
+    // snapshots don't count them again
+    if counted_from < window_start {
+        let (early_views, early_taps) = if snapshot_ts <= window_start {
+            (new_views, new_taps)
+        } else {
+            let stretch = snapshot_ts - counted_from;
+            let early = window_start - counted_from;
+            (
+                pro_rata(new_views, early, stretch)?,
+                pro_rata(new_taps, early, stretch)?,
+            )
+        };
+        performance.baseline_views = performance
+            .baseline_views
+            .checked_add(early_views)
+            .ok_or(ErrorCode::CalculationError)?;
+        performance.baseline_taps = performance
+            .baseline_taps
+            .checked_add(early_taps)
+            .ok_or(ErrorCode::CalculationError)?;
+        new_views -= early_views;
+        new_taps -= early_taps;
+        counted_from = window_start.min(snapshot_ts.max(counted_from));
+    }
+    let counted_until = if snapshot_ts <= window_end {
+        snapshot_ts.max(counted_from)
+    } else {
+        let stretch = snapshot_ts - counted_from;
+        new_views = pro_rata(new_views, window_end - counted_from, stretch)?;
+        new_taps = pro_rata(new_taps, window_end - counted_from, stretch)?;
+        window_end
+    };
+
+    performance.total_views = performance
+        .total_views
+        .checked_add(new_views)
+        .ok_or(ErrorCode::CalculationError)?;
+    performance.total_taps = performance
+        .total_taps
+        .checked_add(new_taps)
+        .ok_or(ErrorCode::CalculationError)?;
+    performance.attributed_until = counted_until;
+    Ok(())
+}
+
+// `amount` scaled by part / whole, rounding down
+fn pro_rata(amount: u64, part: i64, whole: i64) -> Result<u64> {
+    if whole <= 0 {
+        return Ok(0);
+    }
+    let scaled = (amount as u128)
+        .checked_mul(part.clamp(0, whole) as u128)
+        .ok_or(ErrorCode::CalculationError)?
+        / whole as u128;
+    Ok(scaled as u64)
+}
+
+// Hours in [from_hour, to_hour) that map onto the feed history ring slot `slot`
+fn hours_in_slot(slot: i64, from_hour: i64, to_hour: i64) -> i64 {
+    if to_hour <= from_hour {
+        return 0;
+    }
+    (to_hour - 1 - slot).div_euclid(FEED_HISTORY_HOURS)
+        - (from_hour - 1 - slot).div_euclid(FEED_HISTORY_HOURS)
+}
+
+// Optional oracle feed accounts must be passed together or not at all
+fn oracle_accounts<'a, 'info>(
+    device_feed: Option<&'a Account<'info, DeviceFeed>>,
+    oracle_program: Option<&'a Program<'info, oracle::program::Oracle>>,
+) -> Result<
+    Option<(
+        &'a Account<'info, DeviceFeed>,
+        &'a Program<'info, oracle::program::Oracle>,
+    )>,
+> {
+    match (device_feed, oracle_program) {
+        (Some(device_feed), Some(oracle_program)) => Ok(Some((device_feed, oracle_program))),
+        (None, None) => Ok(None),
+        _ => err!(ErrorCode::OracleAccountsRequired),
+    }
+}
+
+// Check a user-supplied string fits its account field and isn't blank or
+// full of control characters
+fn validate_text(value: &str, max_len: usize, empty: ErrorCode, too_long: ErrorCode) -> Result<()> {
+    if value.trim().is_empty() {
+        return Err(empty.into());
+    }
+    if value.len() > max_len {
+        return Err(too_long.into());
+    }
+    require!(
+        !value.chars().any(char::is_control),
+        ErrorCode::InvalidCharacters
+    );
+    Ok(())
+}
+
+// Basic shape check for a contact email: local@domain.tld without whitespace
+fn validate_email(email: &str) -> Result<()> {
+    require!(email.len() <= MAX_EMAIL_LENGTH, ErrorCode::EmailTooLong);
+
+    let well_formed = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && domain.contains('.')
+                && domain.split('.').all(|label| !label.is_empty())
+                && !domain.contains('@')
+                && !email.chars().any(|c| c.is_whitespace() || c.is_control())
+        }
+        None => false,
+    };
+    require!(well_formed, ErrorCode::InvalidEmail);
+    Ok(())
+}
+
+// Platform fee owed on `amount` at `fee_bps` basis points
+fn calculate_platform_fee(amount: u64, fee_bps: u16) -> Result<u64> {
+    let fee = amount
+        .checked_mul(fee_bps as u64)
+        .ok_or(ErrorCode::CalculationError)?
+        .checked_div(BPS_DENOMINATOR)
+        .ok_or(ErrorCode::CalculationError)?;
+    Ok(fee)
+}
+
+// Move `amount` of a campaign's budget out of its vault. SOL is debited from the
+// vault's own lamports, which never dips into its rent reserve; SPL budgets are
+// transferred from the vault-owned token account.
+fn pay_from_vault<'info>(
+    campaign: &Campaign,
+    campaign_vault: &Account<'info, CampaignVault>,
+    recipient: &AccountInfo<'info>,
+    campaign_token_account: Option<&Account<'info, TokenAccount>>,
+    recipient_token_account: Option<&Account<'info, TokenAccount>>,
+    token_program: Option<&Program<'info, Token>>,
+    amount: u64,
+) -> Result<()> {
+    if campaign.budget_mint.is_some() {
+        let (Some(from), Some(to), Some(token_program)) =
+            (campaign_token_account, recipient_token_account, token_program)
+        else {
+            return err!(ErrorCode::TokenAccountsRequired);
+        };
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"campaign_vault".as_ref(),
+            campaign_vault.campaign.as_ref(),
+            &[campaign_vault.bump],
+        ]];
+        let cpi_context = CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            token::Transfer {
+                from: from.to_account_info(),
+                to: to.to_account_info(),
+                authority: campaign_vault.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token::transfer(cpi_context, amount)
+    } else {
+        let vault_info = campaign_vault.to_account_info();
+        let rent_reserve = Rent::get()?.minimum_balance(vault_info.data_len());
+        let available = vault_info.lamports().saturating_sub(rent_reserve);
+        require!(amount <= available, ErrorCode::InsufficientVaultBalance);
+
+        **vault_info.try_borrow_mut_lamports()? -= amount;
+        **recipient.try_borrow_mut_lamports()? += amount;
+        Ok(())
     }
 }
 
@@ -505,12 +2254,14 @@ pub enum ErrorCode {
     RegistryNotInitialized,
     #[msg("Provider not found in registry")]
     ProviderNotInRegistry,
-    #[msg("Registry is full, cannot add more providers")]
+    #[msg("Registry page is full, register on the next page")]
     RegistryFull,
     #[msg("Calculation error")]
     CalculationError,
+    // No longer raised (settlement pro-rates a short budget); kept so later codes keep their numbers
     #[msg("Insufficient budget")]
     InsufficientBudget,
+    // No longer raised (campaigns without views settle too); kept so later codes keep their numbers
     #[msg("No views recorded")]
     NoViews,
     #[msg("Campaign not completed")]
@@ -521,4 +2272,89 @@ pub enum ErrorCode {
     ProviderNotInCampaign,
     #[msg("No earnings to withdraw")]
     NoEarningsToWithdraw,
+    #[msg("Token accounts are required for SPL budget campaigns")]
+    TokenAccountsRequired,
+    #[msg("Campaign vault does not hold enough funds")]
+    InsufficientVaultBalance,
+    #[msg("Campaign fees have already been calculated")]
+    FeesAlreadyCalculated,
+    #[msg("Campaign fees have not been calculated yet")]
+    FeesNotCalculated,
+    #[msg("No platform fee to collect")]
+    NoPlatformFeeToCollect,
+    #[msg("Platform fee exceeds the maximum allowed")]
+    InvalidPlatformFee,
+    // No longer raised (unclaimed earnings are forfeited after the window); kept so later codes keep their numbers
+    #[msg("Providers can still claim earnings from this campaign")]
+    ClaimWindowOpen,
+    #[msg("No budget left to refund")]
+    NoBudgetToRefund,
+    #[msg("Earnings from this campaign have already been withdrawn")]
+    EarningsAlreadyClaimed,
+    #[msg("Providers have not withdrawn all earnings")]
+    EarningsNotWithdrawn,
+    #[msg("Platform fee has not been collected")]
+    PlatformFeeNotCollected,
+    #[msg("Remaining accounts do not match the campaign's devices")]
+    InvalidRemainingAccounts,
+    #[msg("Device is not in the expected state")]
+    InvalidDeviceState,
+    #[msg("Campaign not paused")]
+    CampaignNotPaused,
+    #[msg("Campaign must end after it starts and in the future")]
+    InvalidCampaignSchedule,
+    #[msg("Campaign has not reached its end time")]
+    CampaignNotEnded,
+    #[msg("Registry page does not exist yet and is not the next page")]
+    InvalidRegistryPage,
+    #[msg("Device order is not pending")]
+    OrderNotPending,
+    #[msg("Device feed and oracle program must be passed together")]
+    OracleAccountsRequired,
+    #[msg("Device cannot be transferred to its current owner")]
+    InvalidTransferTarget,
+    #[msg("No transfer of this device is pending for the caller")]
+    NoPendingTransfer,
+    #[msg("Signer does not control this provider")]
+    NotProviderAuthority,
+    #[msg("New authority must be set and differ from the current one")]
+    InvalidNewAuthority,
+    #[msg("Provider still owns devices; decommission or transfer them first")]
+    ProviderHasDevices,
+    #[msg("Provider has earnings left to withdraw")]
+    ProviderHasPendingPayments,
+    #[msg("Name must not be empty")]
+    NameEmpty,
+    #[msg("Name is longer than 32 bytes")]
+    NameTooLong,
+    #[msg("Location must not be empty")]
+    LocationEmpty,
+    #[msg("Location is longer than 64 bytes")]
+    LocationTooLong,
+    #[msg("Contact email is longer than 32 bytes")]
+    EmailTooLong,
+    #[msg("Contact email is not a valid address")]
+    InvalidEmail,
+    #[msg("Campaign name must not be empty")]
+    CampaignNameEmpty,
+    #[msg("Campaign name is longer than 20 bytes")]
+    CampaignNameTooLong,
+    #[msg("Campaign description is longer than 100 bytes")]
+    CampaignDescriptionTooLong,
+    #[msg("Text contains control characters")]
+    InvalidCharacters,
+    #[msg("Review score must be between 0 and 100")]
+    InvalidReviewScore,
+    #[msg("Device uptime was checked too recently")]
+    UptimeCheckTooSoon,
+    #[msg("Device feed keeps history; pass its feed_history account")]
+    FeedHistoryRequired,
+    #[msg("Campaign running hours don't fit between its start and end")]
+    ScheduleExceedsWindow,
+    #[msg("Device feed is controlled by a different wallet than the provider")]
+    FeedAuthorityMismatch,
+    #[msg("Only providers the campaign paid can be reviewed")]
+    ProviderNotPaid,
+    #[msg("Campaign devices must be released by sync_campaign_device first")]
+    BookingNotReleased,
 }
\ No newline at end of file
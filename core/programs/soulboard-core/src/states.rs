@@ -1,11 +1,39 @@
 use anchor_lang::prelude::*;
 
-//Keeps track of all the devices in the soulboard system
+//Keeps track of a single device in the soulboard system
 //Device id is the channel id of the device
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
-pub struct Soulboard {
+#[account]
+#[derive(InitSpace)]
+pub struct Device {
+    pub authority: Pubkey,         //Provider key of the owner (AdProvider.provider_key)
+    pub provider: Pubkey,          //AdProvider PDA of the owner
     pub device_id: u32,            //Channel id of the device
     pub device_state: DeviceState, //State of the device
+    #[max_len(64)]
+    pub location: String,          //Where the device is installed
+    pub specs: DeviceSpecs,        //Screen specifications
+    pub current_campaign: Option<Pubkey>, //Campaign the device is booked for
+    pub pending_owner: Option<Pubkey>,    //Provider key a transfer was proposed to
+    pub last_uptime_check: i64,           //Start of the first hour the uptime crank hasn't counted yet
+    pub bump: u8,
+}
+
+//Global index entry for a device id, so each channel id has exactly one owner
+#[account]
+#[derive(InitSpace)]
+pub struct DeviceIndex {
+    pub device_id: u32,
+    pub authority: Pubkey,   //Provider key of the owner
+    pub ad_provider: Pubkey, //AdProvider PDA of the owner
+    pub device: Pubkey,      //Device PDA
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct DeviceSpecs {
+    pub resolution_width: u32,   // Pixels
+    pub resolution_height: u32,  // Pixels
+    pub screen_size_inches: u16, // Diagonal
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace, PartialEq, Eq)]
@@ -21,29 +49,58 @@ pub enum DeviceState {
 #[account]
 #[derive(InitSpace)]
 pub struct AdProvider {
-    pub authority: Pubkey,
-    #[max_len(10)] // Reduced from 20 to 10 devices
-    pub devices: Vec<Soulboard>,
+    pub authority: Pubkey, // Wallet currently controlling the provider
     #[max_len(32)] // Reduced from 50 to 32
     pub name: String, // Provider name
     #[max_len(64)] // Reduced from 100 to 64
     pub location: String, // Provider location
     #[max_len(32)] // Reduced from 50 to 32
     pub contact_email: String, // Contact information
-    pub rating: u8,           // Rating out of 100
+    pub rating: u8,           // Rating out of 100, derived from the reputation counters below
     pub total_campaigns: u32, // Total campaigns served
     pub is_active: bool,      // Active status
     pub total_earnings: u64,  // Total earnings in lamports
     pub pending_payments: u64, // Pending payments to be withdrawn
+    pub registry_page: u32,   // Registry page listing this provider
+    pub provider_key: Pubkey, // Key the provider's PDAs are seeded by, fixed at registration
+    pub recovery_authority: Option<Pubkey>, // Backup key allowed to rotate the authority
+    pub review_count: u32,        // Advertiser reviews received
+    pub review_score_total: u64,  // Sum of review scores (0-100 each)
+    pub uptime_checks: u32,       // Device hours checked for uptime across the provider's devices
+    pub uptime_hits: u32,         // Checked hours in which the device's feed was updated
+    pub fulfilled_bookings: u32,  // Paid bookings in campaigns that ran to completion
+    pub cancelled_bookings: u32,  // Bookings the provider cancelled; advertiser cancellations don't count
+}
+
+// One advertiser review of a provider for a campaign
+#[account]
+#[derive(InitSpace)]
+pub struct Review {
+    pub campaign: Pubkey,
+    pub provider: Pubkey, // Provider key
+    pub reviewer: Pubkey, // Campaign authority
+    pub score: u8,        // 0-100
+    pub created_at: i64,
+    pub bump: u8,
 }
 
-// Global registry to track all providers
+// Global registry to track all providers. The provider list itself is
+// sharded across RegistryPage accounts so it can grow past one account.
 #[account]
 #[derive(InitSpace)]
 pub struct ProviderRegistry {
     pub total_providers: u32,
-    #[max_len(50)] // Reduced from 1000 to 50 to stay under size limits
-    pub providers: Vec<Pubkey>, // List of all provider authorities
+    pub page_count: u32, // Number of RegistryPage accounts created so far
+}
+
+// One page of the provider registry, seeded by its index
+#[account]
+#[derive(InitSpace)]
+pub struct RegistryPage {
+    pub page_index: u32,
+    #[max_len(50)] // MAX_PROVIDERS_PER_REGISTRY_PAGE
+    pub providers: Vec<Pubkey>, // Provider keys listed on this page
+    pub bump: u8,
 }
 
 // Provider metadata for easier querying
@@ -60,6 +117,7 @@ pub struct ProviderMetadata {
     pub available_devices: u32,
     pub rating: u8,
     pub is_active: bool,
+    pub provider_key: Pubkey, // Matches AdProvider.provider_key
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace, PartialEq, Eq)]
@@ -67,18 +125,27 @@ pub enum CampaignStatus {
     Active,
     Paused,
     Completed,
+    Cancelled,
 }
 
 // Provider performance tracking within a campaign
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
 pub struct ProviderPerformance {
-    pub provider: Pubkey,           // Provider authority
+    pub provider: Pubkey,           // Provider key
     pub device_id: u32,            // Device being used
+    pub device: Pubkey,            // Device PDA being used
     pub total_views: u64,          // Total views from oracle
     pub total_taps: u64,           // Total taps from oracle
     pub calculated_earnings: u64,   // Final calculated earnings
     pub base_fee_earned: u64,      // Base fee portion
     pub performance_fee_earned: u64, // Performance-based portion
+    pub withdrawn_amount: u64,     // Earnings already paid out
+    pub claimed: bool,             // Set once calculated_earnings is fully withdrawn
+    pub booked_at: i64,            // When the device was added to the campaign
+    pub attributed_until: i64,     // Feed views up to this time are already counted
+    pub baseline_views: u64,       // Feed lifetime views not credited to the campaign
+    pub baseline_taps: u64,        // Feed lifetime taps not credited to the campaign
+    pub released: bool,            // Set once settled: device freed and earnings credited to the provider
 }
 
 #[account]
@@ -90,7 +157,8 @@ pub struct Campaign {
     pub campaign_name: String,
     #[max_len(100)]
     pub campaign_description: String,
-    pub campaign_budget: u64, //in lamports
+    pub campaign_budget: u64, //in lamports, or base units of budget_mint
+    pub budget_mint: Option<Pubkey>, // SPL mint the budget is held in, None for SOL
     pub campaign_status: CampaignStatus,
     #[max_len(20)]
     pub campaign_providers: Vec<Pubkey>,
@@ -100,15 +168,48 @@ pub struct Campaign {
     // Fee calculation fields
     pub running_days: u32,         // Total running days
     pub hours_per_day: u32,        // Hours per day
+    pub start_ts: i64,             // Scheduled start (unix timestamp)
+    pub end_ts: i64,               // Scheduled end (unix timestamp)
+    pub ended_at: i64,             // When the campaign actually stopped running
+    pub paused_at: i64,            // When the current pause started, 0 if not paused
+    pub total_paused_seconds: u64, // Time spent paused, excluded from base fees
     pub base_fee_per_hour: u64,    // Base fee per hour in lamports
-    pub platform_fee: u64,         // Platform fee (2% of budget)
+    pub platform_fee: u64,         // Platform fee (fee_bps of budget)
+    pub platform_fee_bps_override: Option<u16>, // Per-campaign fee set by the admin
     pub total_distributed: u64,    // Total amount distributed to providers
+    pub fees_calculated: bool,     // Set once earnings have been settled
+    pub settled_at: i64,           // When fees were calculated
+    pub total_withdrawn: u64,      // Earnings providers have withdrawn so far
+    pub platform_fee_collected: u64, // Platform fee already moved to the treasury
+    pub budget_refunded: u64,      // Undistributed budget returned to the advertiser
+    pub earnings_forfeited: u64,   // Unclaimed earnings returned after the claim window
     
     // Performance tracking
     #[max_len(20)]
     pub campaign_performance: Vec<ProviderPerformance>,
 }
 
+// Global platform settings, including where collected fees are sent
+#[account]
+#[derive(InitSpace)]
+pub struct PlatformConfig {
+    pub admin: Pubkey,    // Key allowed to collect fees and manage the platform
+    pub treasury: Pubkey, // Wallet receiving platform fees
+    pub platform_fee_bps: u16, // Default platform fee in basis points
+    pub fulfilment_authority: Pubkey, // Key that confirms device deliveries
+    pub device_price: u64, // Hardware price in lamports charged per order, 0 for free
+    pub bump: u8,
+}
+
+// Escrow holding only a campaign's budget. SOL budgets sit in its lamports
+// (above its own rent reserve), SPL budgets in the token account it owns.
+#[account]
+#[derive(InitSpace)]
+pub struct CampaignVault {
+    pub campaign: Pubkey,
+    pub bump: u8,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace, PartialEq, Eq)]
 pub enum OrderStatus {
     Ordered,
@@ -124,6 +225,10 @@ pub struct DeviceOrder {
     pub authority: Pubkey,
     pub device_id: u32,
     pub order_status: OrderStatus,
+    pub amount_paid: u64, // Hardware payment held until delivery
+    pub ordered_at: i64,
+    pub delivered_at: i64,
+    pub bump: u8,
 }
 
 // Events
@@ -133,11 +238,98 @@ pub struct DeviceOrdered {
     pub device_state: DeviceState,
 }
 
+#[event]
+pub struct DeviceDelivered {
+    pub device_id: u32,
+    pub authority: Pubkey,
+    pub amount_paid: u64,
+}
+
+#[event]
+pub struct DeviceDecommissioned {
+    pub device_id: u32,
+    pub authority: Pubkey,
+    pub feed_closed: bool,
+}
+
+#[event]
+pub struct DeviceTransferProposed {
+    pub device_id: u32,
+    pub from: Pubkey,
+    pub to: Pubkey,
+}
+
+#[event]
+pub struct DeviceTransferCancelled {
+    pub device_id: u32,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct DeviceTransferred {
+    pub device_id: u32,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub feed_transferred: bool,
+}
+
+#[event]
+pub struct DeviceOrderCancelled {
+    pub device_id: u32,
+    pub authority: Pubkey,
+    pub amount_refunded: u64,
+}
+
 #[event]
 pub struct ProviderRegistered {
     pub authority: Pubkey,
     pub name: String,
     pub location: String,
+    pub registry_page: u32,
+}
+
+#[event]
+pub struct ReviewSubmitted {
+    pub campaign: Pubkey,
+    pub provider_key: Pubkey,
+    pub score: u8,
+}
+
+#[event]
+pub struct DeviceUptimeChecked {
+    pub device_id: u32,
+    pub hours_checked: u32,
+    pub hours_online: u32,
+    pub ts: i64,
+}
+
+#[event]
+pub struct RatingUpdated {
+    pub provider_key: Pubkey,
+    pub previous_rating: u8,
+    pub rating: u8,
+}
+
+#[event]
+pub struct ProviderDeregistered {
+    pub provider_key: Pubkey,
+    pub authority: Pubkey,
+    pub registry_page: u32,
+}
+
+#[event]
+pub struct RecoveryAuthoritySet {
+    pub provider_key: Pubkey,
+    pub recovery_authority: Option<Pubkey>,
+}
+
+#[event]
+pub struct ProviderAuthorityRotated {
+    pub provider_key: Pubkey,
+    pub previous_authority: Pubkey,
+    pub new_authority: Pubkey,
+    pub by_recovery: bool,
+    pub feeds_rotated: u32,
 }
 
 #[event]
@@ -151,6 +343,37 @@ pub struct CampaignCompleted {
     pub authority: Pubkey,
 }
 
+#[event]
+pub struct CampaignCancelled {
+    pub campaign_id: u32,
+    pub authority: Pubkey,
+    pub total_distributed: u64,
+    pub refunded: u64,
+    pub ts: i64,
+}
+
+#[event]
+pub struct CampaignPaused {
+    pub campaign_id: u32,
+    pub authority: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct CampaignResumed {
+    pub campaign_id: u32,
+    pub authority: Pubkey,
+    pub ts: i64,
+}
+
+#[event]
+pub struct CampaignDeviceSynced {
+    pub campaign_id: u32,
+    pub device_id: u32,
+    pub device_state: DeviceState,
+    pub earnings_credited: u64, // Added to the provider's pending payments on release
+}
+
 #[event]
 pub struct BudgetAdded {
     pub authority: Pubkey,
@@ -185,6 +408,7 @@ pub struct PerformanceUpdated {
     pub device_id: u32,
     pub total_views: u64,
     pub total_taps: u64,
+    pub hours_expired: u32, // window hours that left the feed history before being counted
 }
 
 #[event]
@@ -193,6 +417,51 @@ pub struct FeesCalculated {
     pub total_distributed: u64,
 }
 
+#[event]
+pub struct PlatformConfigInitialized {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub fulfilment_authority: Pubkey,
+}
+
+#[event]
+pub struct PlatformConfigUpdated {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub platform_fee_bps: u16,
+    pub fulfilment_authority: Pubkey,
+    pub device_price: u64,
+}
+
+#[event]
+pub struct CampaignFeeOverrideSet {
+    pub campaign: Pubkey,
+    pub platform_fee_bps_override: Option<u16>,
+}
+
+#[event]
+pub struct PlatformFeeCollected {
+    pub campaign: Pubkey,
+    pub campaign_id: u32,
+    pub treasury: Pubkey,
+    pub budget_mint: Option<Pubkey>,
+    pub amount: u64,
+}
+
+#[event]
+pub struct CampaignClosed {
+    pub campaign_id: u32,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct BudgetRefunded {
+    pub campaign_id: u32,
+    pub authority: Pubkey,
+    pub amount: u64,
+    pub earnings_forfeited: u64,
+}
+
 #[event]
 pub struct EarningsWithdrawn {
     pub provider: Pubkey,
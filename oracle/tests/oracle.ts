@@ -46,9 +46,12 @@ const program = anchor.workspace
   .Oracle as anchor.Program<Oracle>;
 
 const DEVICE_SEED = Buffer.from("device_feed");
+const HISTORY_SEED = Buffer.from("feed_history");
+const HISTORY_HOURS = 168;
 
 // Will be derived in before() hook
 let feedPda: anchor.web3.PublicKey;
+let historyPda: anchor.web3.PublicKey;
 let bump!: number;
 
 /* ────────────────────────── test-suite ─────────────────────── */
@@ -62,6 +65,10 @@ describe(`oracle-depin program (channel ${CHANNEL_ID})`, () => {
       ],
       program.programId
     );
+    [historyPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [HISTORY_SEED, new BN(CHANNEL_ID).toArrayLike(Buffer, "le", 4)],
+      program.programId
+    );
   });
 
   it("initialises the device feed and emits DeviceFeedInitialized", async () => {
@@ -194,4 +201,427 @@ describe(`oracle-depin program (channel ${CHANNEL_ID})`, () => {
     );
     assert.equal(acc.lastEntryId, NEW_ENTRY_ID);
   });
+
+  it("commits a keeper update only once the threshold is reached", async () => {
+    const keepers = [0, 1, 2].map(() => anchor.web3.Keypair.generate());
+    const ENTRY_ID = 110;
+
+    await program.methods
+      .setFeedKeepers(CHANNEL_ID, keepers.map((k) => k.publicKey), 2)
+      .accounts({ feed: feedPda, authority: wallet.publicKey })
+      .rpc();
+
+    const before = await program.account.deviceFeed.fetch(feedPda);
+    const attest = (keeper: anchor.web3.Keypair) =>
+      program.methods
+        .updateDeviceFeed(CHANNEL_ID, ENTRY_ID, new BN(NEXT_VIEWS), new BN(NEXT_TAPS))
+        .accounts({ feed: feedPda, signer: keeper.publicKey })
+        .signers([keeper])
+        .rpc();
+
+    await attest(keepers[0]);
+    let acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.equal(acc.totalViews.toString(), before.totalViews.toString());
+    assert.equal(acc.pendingUpdates.length, 1);
+    assert.equal(acc.pendingUpdates[0].attestations, 1);
+
+    try {
+      await attest(keepers[0]);
+      assert.fail("second attestation from the same keeper should fail");
+    } catch (err: any) {
+      expect(err.error.errorCode.code).to.equal("AlreadyAttested");
+    }
+
+    await attest(keepers[1]);
+    acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.equal(
+      acc.totalViews.toString(),
+      (BigInt(before.totalViews.toString()) + NEXT_VIEWS).toString()
+    );
+    assert.equal(acc.lastEntryId, ENTRY_ID);
+    assert.equal(acc.pendingUpdates.length, 0);
+
+    // back to single-authority updates for the remaining tests
+    await program.methods
+      .setFeedKeepers(CHANNEL_ID, [], 0)
+      .accounts({ feed: feedPda, authority: wallet.publicKey })
+      .rpc();
+  });
+
+  it("keeps other keepers' attestations when one keeper submits a different entry", async () => {
+    const keepers = [0, 1, 2].map(() => anchor.web3.Keypair.generate());
+    const before = await program.account.deviceFeed.fetch(feedPda);
+    const ENTRY_ID = before.lastEntryId + 5;
+
+    await program.methods
+      .setFeedKeepers(CHANNEL_ID, keepers.map((k) => k.publicKey), 2)
+      .accounts({ feed: feedPda, authority: wallet.publicKey })
+      .rpc();
+
+    const attest = (keeper: anchor.web3.Keypair, entryId: number, views: bigint) =>
+      program.methods
+        .updateDeviceFeed(CHANNEL_ID, entryId, new BN(views), new BN(NEXT_TAPS))
+        .accounts({ feed: feedPda, signer: keeper.publicKey })
+        .signers([keeper])
+        .rpc();
+
+    await attest(keepers[0], ENTRY_ID, NEXT_VIEWS);
+    // a conflicting entry from another keeper is tracked alongside, not over it
+    await attest(keepers[1], ENTRY_ID + 1, NEXT_VIEWS * 100n);
+    let acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.equal(acc.pendingUpdates.length, 2);
+    assert.equal(acc.totalViews.toString(), before.totalViews.toString());
+
+    // a keeper can't move its vote back to an older entry
+    try {
+      await attest(keepers[1], ENTRY_ID, NEXT_VIEWS);
+      assert.fail("moving a vote to an older entry should fail");
+    } catch (err: any) {
+      expect(err.error.errorCode.code).to.equal("AlreadyAttested");
+    }
+
+    await attest(keepers[2], ENTRY_ID, NEXT_VIEWS);
+    acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.equal(
+      acc.totalViews.toString(),
+      (BigInt(before.totalViews.toString()) + NEXT_VIEWS).toString()
+    );
+    assert.equal(acc.lastEntryId, ENTRY_ID);
+    // the newer candidate survives the commit
+    assert.equal(acc.pendingUpdates.length, 1);
+    assert.equal(acc.pendingUpdates[0].entryId, ENTRY_ID + 1);
+
+    await program.methods
+      .setFeedKeepers(CHANNEL_ID, [], 0)
+      .accounts({ feed: feedPda, authority: wallet.publicKey })
+      .rpc();
+  });
+
+  it("rejects a threshold above the keeper count (InvalidThreshold)", async () => {
+    try {
+      await program.methods
+        .setFeedKeepers(CHANNEL_ID, [anchor.web3.Keypair.generate().publicKey], 2)
+        .accounts({ feed: feedPda, authority: wallet.publicKey })
+        .rpc();
+      assert.fail("threshold above keeper count should fail");
+    } catch (err: any) {
+      expect(err.error.errorCode.code).to.equal("InvalidThreshold");
+    }
+  });
+
+  it("accepts only device-signed counts once a device key is registered", async () => {
+    const device = anchor.web3.Keypair.generate();
+    const ENTRY_ID = 120;
+
+    await program.methods
+      .setDeviceKey(CHANNEL_ID, device.publicKey)
+      .accounts({ feed: feedPda, authority: wallet.publicKey })
+      .rpc();
+
+    const attestation = (views: bigint) =>
+      Buffer.concat([
+        Buffer.from("soulboard-feed"),
+        new BN(CHANNEL_ID).toArrayLike(Buffer, "le", 4),
+        new BN(ENTRY_ID).toArrayLike(Buffer, "le", 4),
+        new BN(views.toString()).toArrayLike(Buffer, "le", 8),
+        new BN(NEXT_TAPS.toString()).toArrayLike(Buffer, "le", 8),
+      ]);
+    const update = (signedViews: bigint, claimedViews: bigint) =>
+      program.methods
+        .updateDeviceFeed(CHANNEL_ID, ENTRY_ID, new BN(claimedViews), new BN(NEXT_TAPS))
+        .accounts({ feed: feedPda, signer: wallet.publicKey })
+        .preInstructions([
+          anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: device.secretKey,
+            message: attestation(signedViews),
+          }),
+        ])
+        .rpc();
+
+    // the keeper inflates the views the device signed for
+    try {
+      await update(NEXT_VIEWS, NEXT_VIEWS * 10n);
+      assert.fail("inflated update should fail");
+    } catch (err: any) {
+      expect(err.error.errorCode.code).to.equal("InvalidDeviceSignature");
+    }
+
+    await update(NEXT_VIEWS, NEXT_VIEWS);
+    const acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.equal(acc.lastEntryId, ENTRY_ID);
+
+    // the update authority alone can't swap the registered key out
+    try {
+      await program.methods
+        .setDeviceKey(CHANNEL_ID, anchor.web3.Keypair.generate().publicKey)
+        .accounts({ feed: feedPda, authority: wallet.publicKey })
+        .rpc();
+      assert.fail("replacing the device key without its signature should fail");
+    } catch (err: any) {
+      expect(err.error.errorCode.code).to.equal("DeviceKeySignatureRequired");
+    }
+
+    const keySetEvt = new Promise<any>((resolve) => {
+      const listener = program.addEventListener("deviceKeySet", (ev) => {
+        program.removeEventListener(listener).catch(() => { });
+        resolve(ev);
+      });
+    });
+    await program.methods
+      .setDeviceKey(CHANNEL_ID, anchor.web3.PublicKey.default)
+      .accounts({
+        feed: feedPda,
+        authority: wallet.publicKey,
+        currentDevice: device.publicKey,
+      })
+      .signers([device])
+      .rpc();
+    const ev = await keySetEvt;
+    assert.ok(ev.previousDeviceKey.equals(device.publicKey));
+    assert.ok(ev.deviceKey.equals(anchor.web3.PublicKey.default));
+  });
+
+  it("rotates the feed authority in one step", async () => {
+    const replacement = anchor.web3.Keypair.generate();
+
+    await program.methods
+      .setFeedAuthority(CHANNEL_ID, replacement.publicKey)
+      .accounts({ feed: feedPda, authority: wallet.publicKey })
+      .rpc();
+    let acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.ok(acc.authority.equals(replacement.publicKey));
+
+    await program.methods
+      .setFeedAuthority(CHANNEL_ID, wallet.publicKey)
+      .accounts({ feed: feedPda, authority: replacement.publicKey })
+      .signers([replacement])
+      .rpc();
+    acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.ok(acc.authority.equals(wallet.publicKey));
+  });
+
+  it("hands feed authority over in two steps (and back again)", async () => {
+    const buyer = anchor.web3.Keypair.generate();
+
+    await program.methods
+      .proposeFeedAuthority(CHANNEL_ID, buyer.publicKey)
+      .accounts({ feed: feedPda, authority: wallet.publicKey })
+      .rpc();
+    await program.methods
+      .acceptFeedAuthority(CHANNEL_ID)
+      .accounts({ feed: feedPda, newAuthority: buyer.publicKey })
+      .signers([buyer])
+      .rpc();
+
+    let acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.ok(acc.authority.equals(buyer.publicKey));
+    assert.ok(acc.pendingAuthority.equals(anchor.web3.PublicKey.default));
+
+    // return the feed so the remaining tests keep using the wallet
+    await program.methods
+      .proposeFeedAuthority(CHANNEL_ID, wallet.publicKey)
+      .accounts({ feed: feedPda, authority: buyer.publicKey })
+      .signers([buyer])
+      .rpc();
+    await program.methods
+      .acceptFeedAuthority(CHANNEL_ID)
+      .accounts({ feed: feedPda, newAuthority: wallet.publicKey })
+      .rpc();
+
+    acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.ok(acc.authority.equals(wallet.publicKey));
+  });
+
+  it("rejects accepting without a nomination (NoPendingAuthority)", async () => {
+    try {
+      await program.methods
+        .acceptFeedAuthority(CHANNEL_ID)
+        .accounts({ feed: feedPda, newAuthority: wallet.publicKey })
+        .rpc();
+      assert.fail("accept without nomination should fail");
+    } catch (err: any) {
+      expect(err.error.errorCode.code).to.equal("NoPendingAuthority");
+    }
+  });
+
+  it("buckets committed deltas by hour once the feed keeps history", async () => {
+    const ENTRY_ID = 130;
+
+    await program.methods
+      .initializeFeedHistory(CHANNEL_ID)
+      .accounts({
+        feed: feedPda,
+        history: historyPda,
+        payer: wallet.publicKey,
+        authority: wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .updateDeviceFeed(CHANNEL_ID, ENTRY_ID, new BN(NEXT_VIEWS), new BN(NEXT_TAPS))
+      .accounts({ feed: feedPda, signer: wallet.publicKey, history: historyPda })
+      .rpc();
+
+    const acc = await program.account.deviceFeed.fetch(feedPda);
+    const history = await program.account.feedHistory.fetch(historyPda);
+    const hour = Math.floor(acc.lastUpdateTs.toNumber() / 3600);
+    const bucket = history.buckets[hour % HISTORY_HOURS];
+    assert.isTrue(acc.hasHistory);
+    assert.equal(history.buckets.length, HISTORY_HOURS);
+    assert.equal(bucket.hour.toNumber(), hour);
+    assert.equal(bucket.views.toString(), NEXT_VIEWS.toString());
+    assert.equal(bucket.taps.toString(), NEXT_TAPS.toString());
+
+    // leaving the history out would drop this delta from the buckets
+    try {
+      await program.methods
+        .updateDeviceFeed(CHANNEL_ID, ENTRY_ID + 1, new BN(1), new BN(0))
+        .accounts({ feed: feedPda, signer: wallet.publicKey, history: null })
+        .rpc();
+      assert.fail("update without history should fail");
+    } catch (err: any) {
+      expect(err.error.errorCode.code).to.equal("MissingFeedHistory");
+    }
+  });
+
+  it("rejects updates outside the feed's limits and records them as a FeedAnomaly", async () => {
+    const ENTRY_ID = 140;
+    const update = (entryId: number, views: number, taps: number) =>
+      program.methods
+        .updateDeviceFeed(CHANNEL_ID, entryId, new BN(views), new BN(taps))
+        .accounts({ feed: feedPda, signer: wallet.publicKey, history: historyPda })
+        .rpc();
+    const setLimits = (
+      maxViewsPerSecond: number,
+      minUpdateInterval: number,
+      tapsWithinViews: boolean,
+      limitsAuthority = anchor.web3.PublicKey.default,
+      signer?: anchor.web3.Keypair
+    ) =>
+      program.methods
+        .setFeedLimits(
+          CHANNEL_ID,
+          new BN(maxViewsPerSecond),
+          new BN(minUpdateInterval),
+          tapsWithinViews,
+          limitsAuthority
+        )
+        .accounts({ feed: feedPda, authority: signer ? signer.publicKey : wallet.publicKey })
+        .signers(signer ? [signer] : [])
+        .rpc();
+    const expectRejected = async (attempt: Promise<unknown>, code: string) => {
+      try {
+        await attempt;
+        assert.fail(`expected ${code}`);
+      } catch (err: any) {
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    };
+
+    // the previous test committed an update moments ago
+    await setLimits(0, 3600, false);
+    await expectRejected(update(ENTRY_ID, 1, 0), "UpdateTooFrequent");
+
+    // a stranger's implausible counts are refused before any limit is looked at
+    const stranger = anchor.web3.Keypair.generate();
+    await expectRejected(
+      program.methods
+        .updateDeviceFeed(CHANNEL_ID, ENTRY_ID, new BN(1_000_000_000), new BN(0))
+        .accounts({ feed: feedPda, signer: stranger.publicKey, history: historyPda })
+        .signers([stranger])
+        .rpc(),
+      "BadAuthority"
+    );
+
+    // the platform takes over the limits, so the feed authority can't loosen them
+    const platform = anchor.web3.Keypair.generate();
+    await setLimits(1, 0, true, platform.publicKey);
+    await expectRejected(setLimits(0, 0, false), "BadAuthority");
+
+    const before = await program.account.deviceFeed.fetch(feedPda);
+    await expectRejected(update(ENTRY_ID, 1_000_000_000, 0), "ViewRateExceeded");
+    await expectRejected(update(ENTRY_ID, 0, 1), "TapsExceedViews");
+    let acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.equal(acc.lastEntryId, before.lastEntryId);
+    assert.equal(acc.totalViews.toString(), before.totalViews.toString());
+
+    // the rejected update is put on record separately, without touching the totals
+    const anomalyEvt = new Promise<any>((resolve) => {
+      const listener = program.addEventListener("feedAnomaly", (ev) => {
+        program.removeEventListener(listener).catch(() => { });
+        resolve(ev);
+      });
+    });
+    await program.methods
+      .reportFeedAnomaly(CHANNEL_ID, ENTRY_ID, new BN(1_000_000_000), new BN(0))
+      .accounts({ feed: feedPda, signer: wallet.publicKey })
+      .rpc();
+    const ev = await anomalyEvt;
+    acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.deepEqual(ev.kind, { viewRateExceeded: {} });
+    assert.equal(ev.deltaViews.toString(), "1000000000");
+    assert.ok(ev.elapsed.toNumber() > 0);
+    assert.equal(acc.anomalyCount, before.anomalyCount + 1);
+    assert.ok(acc.lastAnomalyTs.toNumber() > 0);
+    assert.equal(acc.lastEntryId, before.lastEntryId);
+    assert.equal(acc.totalViews.toString(), before.totalViews.toString());
+
+    // counts within the limits are no anomaly
+    await expectRejected(
+      program.methods
+        .reportFeedAnomaly(CHANNEL_ID, ENTRY_ID, new BN(0), new BN(0))
+        .accounts({ feed: feedPda, signer: wallet.publicKey })
+        .rpc(),
+      "WithinFeedLimits"
+    );
+
+    // only the platform can lift the limits again
+    await setLimits(0, 0, false, anchor.web3.PublicKey.default, platform);
+    await update(ENTRY_ID, 1, 0);
+    acc = await program.account.deviceFeed.fetch(feedPda);
+    assert.equal(acc.lastEntryId, ENTRY_ID);
+    assert.ok(acc.limitsAuthority.equals(anchor.web3.PublicKey.default));
+    assert.equal(acc.anomalyCount, before.anomalyCount + 1);
+  });
+
+  it("closes the feed and returns its rent to the receiver", async () => {
+    const device = anchor.web3.Keypair.generate();
+    await program.methods
+      .setDeviceKey(CHANNEL_ID, device.publicKey)
+      .accounts({ feed: feedPda, authority: wallet.publicKey })
+      .rpc();
+
+    // a keyed feed can't be closed (and re-created keyless) by the authority alone
+    try {
+      await program.methods
+        .closeDeviceFeed(CHANNEL_ID)
+        .accounts({
+          feed: feedPda,
+          history: historyPda,
+          authority: wallet.publicKey,
+          receiver: wallet.publicKey,
+        })
+        .rpc();
+      assert.fail("closing a keyed feed without the device's signature should fail");
+    } catch (err: any) {
+      expect(err.error.errorCode.code).to.equal("DeviceKeySignatureRequired");
+    }
+
+    await program.methods
+      .closeDeviceFeed(CHANNEL_ID)
+      .accounts({
+        feed: feedPda,
+        history: historyPda,
+        authority: wallet.publicKey,
+        currentDevice: device.publicKey,
+        receiver: wallet.publicKey,
+      })
+      .signers([device])
+      .rpc();
+
+    const info = await connection.getAccountInfo(feedPda);
+    assert.isNull(info);
+    assert.isNull(await connection.getAccountInfo(historyPda));
+  });
 });
\ No newline at end of file